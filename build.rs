@@ -0,0 +1,10 @@
+/// Generate the gRPC adapter types from the protos, protox compiles them so that protoc isn't needed
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let protos = ["proto/testrpc.proto", "proto/health.proto"];
+    for proto in protos {
+        println!("cargo:rerun-if-changed={proto}");
+    }
+    let fds = protox::compile(protos, ["proto"])?;
+    tonic_build::configure().compile_fds(fds)?;
+    Ok(())
+}
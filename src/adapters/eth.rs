@@ -0,0 +1,771 @@
+/// Ethereum implementation of the adapter, submitting signed transfers with `eth_sendRawTransaction`
+use crate::adapters::Adapter;
+use k256::ecdsa::SigningKey;
+use rand::Rng as _;
+use serde_yaml::Value;
+use sha3::{Digest as _, Keccak256};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::common::{
+    self,
+    broadcast::Broadcasts,
+    burst,
+    payload::{PayloadGenerators, TxPrefix},
+    replay::TxFiles,
+    RoundResults, TestrpcError,
+};
+use crate::config::RoundTemplate;
+use crate::{jrpc, rng};
+
+/// Method submitting a signed transaction
+const SEND_METHOD: &str = "eth_sendRawTransaction";
+/// Method pinged to check that a node is reachable
+const PING_METHOD: &str = "net_version";
+/// Method returning the height of the chain
+const BLOCK_NUMBER_METHOD: &str = "eth_blockNumber";
+/// Gas of a transfer without data
+const TRANSFER_GAS: u64 = 21_000;
+/// Gas of each byte of data, as if none was zero
+const DATA_BYTE_GAS: u64 = 16;
+/// Method returning the nonce of the next transaction of an account
+const NONCE_METHOD: &str = "eth_getTransactionCount";
+
+/// Arguments for the Ethereum adapter
+#[derive(Debug, Clone)]
+pub struct EthArgs {
+    /// Static list of the node urls
+    pub endpoints: Vec<String>,
+    /// Key of the account signing the transactions
+    pub private_key: SigningKey,
+    /// Chain id the transactions are signed for (EIP-155)
+    pub chain_id: u64,
+    /// Nonce of the first transaction, incremented for each one.
+    /// Fetched from the nodes with `eth_getTransactionCount` if None.
+    pub nonce: Option<u64>,
+    /// Fetch the nonce again from the node after failed transactions, to fill the gap they leave.
+    /// Transactions in flight to other nodes may then reuse a nonce and be rejected.
+    pub resync_nonce: bool,
+    /// Recipient of the transfers, the sender itself if None
+    pub to: Option<[u8; 20]>,
+    /// Value of the transfers, in wei
+    pub value: u128,
+    /// Gas price of the transactions, in wei
+    pub gas_price: u128,
+    /// Gas limit of the transactions, enough for a transfer with the data of the template if None
+    pub gas_limit: Option<u64>,
+    /// Generator of the data of the transactions, see `PayloadGenerators::from_args`
+    pub payload_generator: PayloadGenerators,
+    /// Prefix of the generated data, see `TxPrefix::from_args`
+    pub tx_prefix: TxPrefix,
+    /// Transport options of the client, see `jrpc::ClientOptions::from_args`
+    pub client_options: jrpc::ClientOptions,
+}
+
+/// Parse a hex string, with or without `0x`, of exactly `N` bytes
+fn parse_hex<const N: usize>(name: &str, hex_str: &str) -> Result<[u8; N], TestrpcError> {
+    let bytes = hex::decode(hex_str.trim_start_matches("0x"))
+        .map_err(|e| TestrpcError::MissingArgs(format!("{name} must be hex: {e}")))?;
+    bytes
+        .try_into()
+        .map_err(|_| TestrpcError::MissingArgs(format!("{name} must be {N} bytes")))
+}
+
+/// Parse an integer argument given as a number or a decimal string, for values above u64
+fn parse_u128(name: &str, value: &Value) -> Result<u128, TestrpcError> {
+    match value {
+        Value::Number(n) if n.is_u64() => Ok(n.as_u64().unwrap() as u128),
+        Value::String(s) => s
+            .parse()
+            .map_err(|_| TestrpcError::MissingArgs(format!("{name} must be an integer"))),
+        _ => Err(TestrpcError::MissingArgs(format!(
+            "{name} must be an integer"
+        ))),
+    }
+}
+
+impl TryFrom<HashMap<String, Value>> for EthArgs {
+    type Error = TestrpcError;
+
+    fn try_from(args: HashMap<String, Value>) -> Result<Self, Self::Error> {
+        let endpoints = match args.get("endpoints") {
+            Some(Value::Sequence(endpoints)) => endpoints
+                .iter()
+                .map(|endpoint| match endpoint {
+                    Value::String(endpoint) => Ok(endpoint.clone()),
+                    _ => Err(TestrpcError::MissingArgs(
+                        "endpoints must be a list of strings".to_string(),
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => Vec::new(),
+        };
+        let private_key = match args.get("private_key") {
+            Some(Value::String(key)) => {
+                SigningKey::from_bytes(&parse_hex::<32>("private_key", key)?.into())
+                    .map_err(|_| TestrpcError::MissingArgs("invalid private_key".to_string()))?
+            }
+            _ => return Err(TestrpcError::MissingArgs("private_key".to_string())),
+        };
+        let chain_id = match args.get("chain_id") {
+            Some(Value::Number(id)) if id.is_u64() => id.as_u64().unwrap(),
+            _ => return Err(TestrpcError::MissingArgs("chain_id".to_string())),
+        };
+        let nonce = match args.get("nonce") {
+            Some(Value::Number(nonce)) if nonce.is_u64() => nonce.as_u64(),
+            _ => None,
+        };
+        let resync_nonce = matches!(args.get("resync_nonce"), Some(Value::Bool(true)));
+        let to = match args.get("to") {
+            Some(Value::String(to)) => Some(parse_hex::<20>("to", to)?),
+            _ => None,
+        };
+        let value = match args.get("value") {
+            Some(value) => parse_u128("value", value)?,
+            None => 0,
+        };
+        let gas_price = match args.get("gas_price") {
+            Some(price) => parse_u128("gas_price", price)?,
+            None => 1_000_000_000,
+        };
+        let gas_limit = match args.get("gas_limit") {
+            Some(Value::Number(limit)) if limit.is_u64() => limit.as_u64(),
+            _ => None,
+        };
+
+        Ok(EthArgs {
+            endpoints,
+            private_key,
+            chain_id,
+            nonce,
+            resync_nonce,
+            to,
+            value,
+            gas_price,
+            gas_limit,
+            payload_generator: PayloadGenerators::from_args(&args)?,
+            tx_prefix: TxPrefix::from_args(&args)?,
+            client_options: jrpc::ClientOptions::from_args(&args)?,
+        })
+    }
+}
+
+/// Legacy transaction, signed as per EIP-155
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub nonce: u64,
+    pub gas_price: u128,
+    pub gas_limit: u64,
+    pub to: [u8; 20],
+    pub value: u128,
+    pub data: Vec<u8>,
+}
+
+impl Transaction {
+    /// RLP of the fields of the transaction followed by the given trailing fields
+    fn rlp(&self, trailer: [Vec<u8>; 3]) -> Vec<u8> {
+        rlp::list(
+            [
+                rlp::uint(self.nonce as u128),
+                rlp::uint(self.gas_price),
+                rlp::uint(self.gas_limit as u128),
+                rlp::bytes(&self.to),
+                rlp::uint(self.value),
+                rlp::bytes(&self.data),
+            ]
+            .into_iter()
+            .chain(trailer),
+        )
+    }
+
+    /// Sign the transaction for the given chain, returning the raw transaction
+    pub fn sign(&self, key: &SigningKey, chain_id: u64) -> Result<Vec<u8>, TestrpcError> {
+        let hash =
+            Keccak256::digest(self.rlp([rlp::uint(chain_id as u128), rlp::uint(0), rlp::uint(0)]));
+        let (signature, recovery_id) = key
+            .sign_prehash_recoverable(&hash)
+            .map_err(|e| TestrpcError::RpcError(format!("Failed to sign tx: {e}")))?;
+        let v = recovery_id.to_byte() as u128 + chain_id as u128 * 2 + 35;
+        let (r, s) = signature.split_bytes();
+        Ok(self.rlp([
+            rlp::uint(v),
+            rlp::bytes(strip_zeros(&r)),
+            rlp::bytes(strip_zeros(&s)),
+        ]))
+    }
+}
+
+/// Address of the account of the key
+pub fn address(key: &SigningKey) -> [u8; 20] {
+    let public_key = key.verifying_key().to_encoded_point(false);
+    let hash = Keccak256::digest(&public_key.as_bytes()[1..]);
+    hash[12..].try_into().unwrap()
+}
+
+/// Parse a hex quantity, e.g. `0x1a`
+fn parse_quantity(value: &serde_json::Value) -> Option<u64> {
+    u64::from_str_radix(value.as_str()?.strip_prefix("0x")?, 16).ok()
+}
+
+/// Nonces of the transactions of the account, shared by the concurrent sends
+#[derive(Debug, Default)]
+struct Nonces {
+    /// Nonce of the next transaction
+    next: AtomicU64,
+    /// Set by the first send, once the nonce was given or fetched from a node
+    synced: tokio::sync::OnceCell<()>,
+}
+
+impl Nonces {
+    /// Take the nonces of `count` transactions, returning the first one
+    fn take(&self, count: usize) -> u64 {
+        self.next.fetch_add(count as u64, Ordering::SeqCst)
+    }
+
+    /// Continue from the given nonce
+    fn set(&self, next: u64) {
+        self.next.store(next, Ordering::SeqCst);
+    }
+}
+
+/// The bytes without their leading zeros, as RLP encodes integers
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Minimal RLP encoding, enough for the transactions
+mod rlp {
+    /// Encode a byte string
+    pub fn bytes(bytes: &[u8]) -> Vec<u8> {
+        if bytes.len() == 1 && bytes[0] < 0x80 {
+            return bytes.to_vec();
+        }
+        let mut encoded = prefix(0x80, bytes.len());
+        encoded.extend_from_slice(bytes);
+        encoded
+    }
+
+    /// Encode an integer, big endian without leading zeros
+    pub fn uint(value: u128) -> Vec<u8> {
+        bytes(super::strip_zeros(&value.to_be_bytes()))
+    }
+
+    /// Encode a list of encoded items
+    pub fn list(items: impl IntoIterator<Item = Vec<u8>>) -> Vec<u8> {
+        let payload = items.into_iter().flatten().collect::<Vec<_>>();
+        let mut encoded = prefix(0xc0, payload.len());
+        encoded.extend(payload);
+        encoded
+    }
+
+    /// Prefix of a string (`offset` 0x80) or a list (`offset` 0xc0) of the given length
+    fn prefix(offset: u8, len: usize) -> Vec<u8> {
+        if len < 56 {
+            return vec![offset + len as u8];
+        }
+        let len_bytes = super::strip_zeros(&len.to_be_bytes()).to_vec();
+        let mut encoded = vec![offset + 55 + len_bytes.len() as u8];
+        encoded.extend(len_bytes);
+        encoded
+    }
+}
+
+pub struct EthAdapter {
+    args: EthArgs,
+    client: jrpc::Client,
+    /// Address of the account signing the transactions
+    sender: [u8; 20],
+    /// Nonces of the transactions, see `EthArgs::nonce`
+    nonces: Nonces,
+    /// Tx files replayed by the templates with a `tx_source`, as already signed transactions
+    tx_files: TxFiles,
+    /// Txs of the rounds broadcasting identical txs
+    broadcasts: Broadcasts<Vec<u8>>,
+}
+
+impl EthAdapter {
+    pub fn new(args: EthArgs, dry_run: Option<jrpc::DryRun>) -> Result<Self, TestrpcError> {
+        let client = jrpc::Client::new(&jrpc::ClientOptions {
+            dry_run,
+            ..args.client_options.clone()
+        })?;
+        Ok(EthAdapter {
+            sender: address(&args.private_key),
+            nonces: Nonces::default(),
+            args,
+            client,
+            tx_files: TxFiles::default(),
+            broadcasts: Broadcasts::default(),
+        })
+    }
+
+    /// Signed transactions of a request. The requests of a round broadcasting identical txs
+    /// share the txs signed by the first one, with the same nonces.
+    fn signed_txs(
+        &self,
+        iteration: u32,
+        req_id: u64,
+        template: &RoundTemplate,
+    ) -> Result<Vec<Vec<u8>>, TestrpcError> {
+        self.broadcasts.txs(template, || match &template.tx_source {
+            Some(source) => self.tx_files.take(source, template.txs),
+            None => self.sign_txs(iteration, req_id, template),
+        })
+    }
+
+    /// Sign the transactions of a request, the template's tx size being the size of their data
+    fn sign_txs(
+        &self,
+        iteration: u32,
+        req_id: u64,
+        template: &RoundTemplate,
+    ) -> Result<Vec<Vec<u8>>, TestrpcError> {
+        let tx_sizes = template.tx_sizes(iteration, req_id);
+        let rng = template.payload_rng(rng::seed(), iteration, req_id);
+        let mut generator = self
+            .args
+            .tx_prefix
+            .prefixed(self.args.payload_generator.for_request(rng));
+        let first_nonce = self.nonces.take(tx_sizes.len());
+        tx_sizes
+            .into_iter()
+            .enumerate()
+            .map(|(i, tx_size)| {
+                let data = generator.generate(i, tx_size);
+                Transaction {
+                    nonce: first_nonce + i as u64,
+                    gas_price: self.args.gas_price,
+                    gas_limit: self
+                        .args
+                        .gas_limit
+                        .unwrap_or(TRANSFER_GAS + DATA_BYTE_GAS * tx_size as u64),
+                    to: self.args.to.unwrap_or(self.sender),
+                    value: self.args.value,
+                    data,
+                }
+                .sign(&self.args.private_key, self.args.chain_id)
+            })
+            .collect()
+    }
+
+    /// Fetch the nonce of the next transaction of the sender from the node, counting its pending txs
+    async fn fetch_nonce(
+        &self,
+        rpc_url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<u64, TestrpcError> {
+        if self.client.dry_run().is_some() {
+            return Ok(self.nonces.next.load(Ordering::SeqCst));
+        }
+        let req_id = rng::with_rng(|rng| rng.random::<u64>());
+        let response = self
+            .client
+            .send(
+                rpc_url,
+                req_id,
+                NONCE_METHOD,
+                serde_json::json!([format!("0x{}", hex::encode(self.sender)), "pending"]),
+                timeout,
+            )
+            .await?;
+        parse_quantity(response.result())
+            .ok_or_else(|| TestrpcError::RpcError(format!("Invalid nonce from {rpc_url}")))
+    }
+
+    /// Start from the given nonce, or fetch it from the node, unless it was already done
+    async fn sync_nonce(
+        &self,
+        rpc_url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(), TestrpcError> {
+        self.nonces
+            .synced
+            .get_or_try_init(|| async {
+                let nonce = match self.args.nonce {
+                    Some(nonce) => nonce,
+                    None => self.fetch_nonce(rpc_url, timeout).await?,
+                };
+                tracing::info!(
+                    "Sending txs from 0x{} starting at nonce {}",
+                    hex::encode(self.sender),
+                    nonce
+                );
+                self.nonces.set(nonce);
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// Continue from the nonce known by the node after failed transactions, see `EthArgs::resync_nonce`
+    async fn resync_nonce(&self, rpc_url: &str, timeout: Option<Duration>) {
+        match self.fetch_nonce(rpc_url, timeout).await {
+            Ok(nonce) => {
+                tracing::info!("Resyncing nonce to {} from {}", nonce, rpc_url);
+                self.nonces.set(nonce);
+            }
+            Err(e) => tracing::warn!("Failed to resync nonce from {}: {}", rpc_url, e),
+        }
+    }
+
+    /// Submit the raw transactions, in a JSON-RPC batch if there are several
+    async fn send_raw_txs(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        txs: &[Vec<u8>],
+        timeout: Option<Duration>,
+    ) -> RoundResults {
+        let mut results = RoundResults::default();
+        let responses = if let [tx] = txs {
+            let response = self
+                .client
+                .send(
+                    rpc_url,
+                    req_id,
+                    SEND_METHOD,
+                    serde_json::json!([format!("0x{}", hex::encode(tx))]),
+                    timeout,
+                )
+                .await;
+            vec![response]
+        } else {
+            let requests = txs
+                .iter()
+                .enumerate()
+                .map(|(i, tx)| {
+                    jrpc::RpcRequest::new(
+                        req_id.wrapping_add(i as u64),
+                        SEND_METHOD,
+                        serde_json::json!([format!("0x{}", hex::encode(tx))]),
+                    )
+                })
+                .collect();
+            match self.client.send_batch(rpc_url, requests, timeout).await {
+                Ok(responses) => responses,
+                Err(e) => {
+                    tracing::warn!("Failed to send {} txs to {}: {}", txs.len(), rpc_url, e);
+                    results.failed = txs.len();
+                    results.bytes_failed = txs.iter().map(Vec::len).sum();
+                    return results;
+                }
+            }
+        };
+        // All the responses of a batch share the same latency, record it once
+        if let Some(Ok(response)) = responses.iter().find(|r| r.is_ok()) {
+            results.latencies.push(response.elapsed());
+        }
+        for (response, tx) in responses.into_iter().zip(txs) {
+            match response {
+                Ok(_) => {
+                    results.sent += 1;
+                    results.bytes_sent += tx.len();
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to send tx: {}", e);
+                    results.failed += 1;
+                    results.bytes_failed += tx.len();
+                }
+            }
+        }
+        results
+    }
+
+    /// Send the signed transactions one by one, waiting for the arrival delays in between
+    async fn send_one_by_one(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        txs: &[Vec<u8>],
+        delays: &[Duration],
+        timeout: Option<Duration>,
+        cancel: &CancellationToken,
+    ) -> RoundResults {
+        let mut results = RoundResults::default();
+        for (i, tx) in txs.iter().enumerate() {
+            if i > 0 {
+                tokio::select! {
+                    _ = tokio::time::sleep(delays[i - 1]) => {}
+                    _ = cancel.cancelled() => {
+                        tracing::debug!("Cancelled, {} txs left unsent to {}", txs.len() - i, rpc_url);
+                        break;
+                    }
+                }
+            }
+            let tx_results = self
+                .send_raw_txs(
+                    rpc_url,
+                    req_id.wrapping_add(i as u64),
+                    std::slice::from_ref(tx),
+                    timeout,
+                )
+                .await;
+            results.merge(&tx_results);
+        }
+        results
+    }
+}
+
+impl TryFrom<HashMap<String, Value>> for EthAdapter {
+    type Error = TestrpcError;
+
+    fn try_from(args: HashMap<String, Value>) -> Result<Self, Self::Error> {
+        EthAdapter::new(EthArgs::try_from(args)?, None)
+    }
+}
+
+impl Adapter for EthAdapter {
+    async fn load_endpoints(
+        &self,
+        args: HashMap<String, Value>,
+    ) -> Result<Vec<String>, TestrpcError> {
+        let EthArgs { endpoints, .. } = EthArgs::try_from(args)?;
+        if endpoints.is_empty() {
+            return Err(TestrpcError::LoadEndpointsError(
+                "No endpoints declared in args.endpoints".to_string(),
+            ));
+        }
+        Ok(endpoints)
+    }
+
+    async fn ping_endpoint(
+        &self,
+        rpc_url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<bool, TestrpcError> {
+        let req_id = rng::with_rng(|rng| rng.random::<u64>());
+        let _ = self
+            .client
+            .send(rpc_url, req_id, PING_METHOD, serde_json::json!([]), timeout)
+            .await?;
+        Ok(true)
+    }
+
+    fn validate_template(&self, template: &RoundTemplate) -> Result<(), TestrpcError> {
+        self.args.tx_prefix.validate(template)?;
+        let (Some(gas_limit), None) = (self.args.gas_limit, &template.tx_source) else {
+            return Ok(());
+        };
+        let tx_size = template.tx_size_max.unwrap_or(0).max(template.tx_size);
+        let min_gas = TRANSFER_GAS + DATA_BYTE_GAS * tx_size as u64;
+        if gas_limit < min_gas {
+            return Err(TestrpcError::LoadRoundTemplateError(format!(
+                "gas_limit {gas_limit} is below the {min_gas} gas of a tx with {tx_size} bytes of data"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn node_status(
+        &self,
+        rpc_url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<common::NodeStatus, TestrpcError> {
+        let req_id = rng::with_rng(|rng| rng.random::<u64>());
+        let response = self
+            .client
+            .send(
+                rpc_url,
+                req_id,
+                BLOCK_NUMBER_METHOD,
+                serde_json::json!([]),
+                timeout,
+            )
+            .await?;
+        let height = parse_quantity(response.result()).ok_or_else(|| {
+            TestrpcError::RpcError(format!("Invalid block number from {rpc_url}"))
+        })?;
+        Ok(common::NodeStatus {
+            height: Some(height),
+            mempool_size: None,
+        })
+    }
+
+    async fn send_txs(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        iteration: u32,
+        template: &RoundTemplate,
+        timeout: Option<Duration>,
+        cancel: &CancellationToken,
+    ) -> Result<RoundResults, TestrpcError> {
+        if cancel.is_cancelled() {
+            return Ok(RoundResults::default());
+        }
+        if template.tx_source.is_none() {
+            self.sync_nonce(rpc_url, timeout).await?;
+        }
+        let txs = self.signed_txs(iteration, req_id, template)?;
+        let results = match (template.rate, template.arrival_delays(iteration, req_id)) {
+            (Some(rate), _) => {
+                burst::send(txs.len(), rate, cancel, |range| {
+                    self.send_raw_txs(
+                        rpc_url,
+                        req_id.wrapping_add(range.start as u64),
+                        &txs[range],
+                        timeout,
+                    )
+                })
+                .await
+            }
+            (None, Some(delays)) => {
+                self.send_one_by_one(rpc_url, req_id, &txs, &delays, timeout, cancel)
+                    .await
+            }
+            (None, None) => self.send_raw_txs(rpc_url, req_id, &txs, timeout).await,
+        };
+        if results.failed > 0 && self.args.resync_nonce && template.tx_source.is_none() {
+            self.resync_nonce(rpc_url, timeout).await;
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Key of the example of EIP-155
+    const EIP155_KEY: &str = "4646464646464646464646464646464646464646464646464646464646464646";
+
+    fn args() -> HashMap<String, Value> {
+        HashMap::from([
+            ("private_key".to_string(), Value::from(EIP155_KEY)),
+            ("chain_id".to_string(), Value::from(1)),
+        ])
+    }
+
+    #[test]
+    fn test_sign() {
+        // example of EIP-155
+        let key =
+            SigningKey::from_bytes(&parse_hex::<32>("key", EIP155_KEY).unwrap().into()).unwrap();
+        let tx = Transaction {
+            nonce: 9,
+            gas_price: 20_000_000_000,
+            gas_limit: 21_000,
+            to: [0x35; 20],
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+        };
+        assert_eq!(
+            hex::encode(tx.sign(&key, 1).unwrap()),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(
+            hex::encode(address(&key)),
+            "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"
+        );
+    }
+
+    #[test]
+    fn test_rlp() {
+        assert_eq!(rlp::uint(0), vec![0x80]);
+        assert_eq!(rlp::uint(15), vec![0x0f]);
+        assert_eq!(rlp::uint(1024), vec![0x82, 0x04, 0x00]);
+        assert_eq!(rlp::bytes(b"dog"), b"\x83dog".to_vec());
+        let long = vec![0xaa; 60];
+        assert_eq!(rlp::bytes(&long)[..2], [0xb8, 60]);
+        assert_eq!(rlp::list([]), vec![0xc0]);
+    }
+
+    #[test]
+    fn test_args() {
+        let args = EthArgs::try_from(args()).unwrap();
+        assert_eq!((args.chain_id, args.nonce, args.value), (1, None, 0));
+        assert!(args.to.is_none());
+        assert!(args.client_options.headers.is_empty());
+
+        let mut with_headers = self::args();
+        with_headers.insert(
+            "headers".to_string(),
+            serde_yaml::from_str("{Authorization: Bearer token}").unwrap(),
+        );
+        let args = EthArgs::try_from(with_headers).unwrap();
+        assert_eq!(
+            args.client_options
+                .headers
+                .get("Authorization")
+                .map(String::as_str),
+            Some("Bearer token")
+        );
+
+        let mut invalid = self::args();
+        invalid.insert("to".to_string(), Value::from("0x1234"));
+        assert!(EthArgs::try_from(invalid).is_err());
+        assert!(EthArgs::try_from(HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_validate_template() {
+        let mut args = args();
+        args.insert("gas_limit".to_string(), Value::from(21_160));
+        let adapter = EthAdapter::try_from(args).unwrap();
+        let template = |tx_size| RoundTemplate {
+            txs: 1,
+            tx_size,
+            ..Default::default()
+        };
+        assert!(adapter.validate_template(&template(10)).is_ok());
+        assert!(adapter.validate_template(&template(11)).is_err());
+        // without a gas limit, it follows the size of the txs
+        let adapter = EthAdapter::try_from(self::args()).unwrap();
+        assert!(adapter.validate_template(&template(1000)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_txs() {
+        let adapter =
+            EthAdapter::new(EthArgs::try_from(args()).unwrap(), Some(jrpc::DryRun::Noop)).unwrap();
+        let template = RoundTemplate {
+            txs: 3,
+            tx_size: 10,
+            ..Default::default()
+        };
+        let results = adapter
+            .send_txs(
+                "http://localhost:8545",
+                1,
+                0,
+                &template,
+                None,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!((results.sent, results.failed), (3, 0));
+        // the nonces of the next txs follow
+        assert_eq!(adapter.nonces.take(0), 3);
+    }
+
+    #[tokio::test]
+    async fn test_nonces() {
+        let mut args = args();
+        args.insert("nonce".to_string(), Value::from(5));
+        let adapter = EthAdapter::try_from(args).unwrap();
+        // a given nonce isn't fetched, the node is never reached
+        adapter
+            .sync_nonce("http://127.0.0.1:1", None)
+            .await
+            .unwrap();
+        assert_eq!(adapter.nonces.take(2), 5);
+        assert_eq!(adapter.nonces.take(1), 7);
+        adapter.nonces.set(6);
+        assert_eq!(adapter.nonces.take(1), 6);
+        // synced once, the next sends continue from the taken nonces
+        adapter
+            .sync_nonce("http://127.0.0.1:1", None)
+            .await
+            .unwrap();
+        assert_eq!(adapter.nonces.take(1), 7);
+
+        assert_eq!(parse_quantity(&serde_json::json!("0x1a")), Some(26));
+        assert_eq!(parse_quantity(&serde_json::json!("1a")), None);
+    }
+}
@@ -0,0 +1,311 @@
+/// gRPC implementation of the adapter, streaming the transactions to a tx-submission service
+use crate::adapters::Adapter;
+use prost::Message as _;
+use serde_yaml::Value;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tonic::{
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    transport::{Channel, Endpoint},
+};
+
+use crate::common::{
+    broadcast::Broadcasts,
+    payload::{PayloadGenerators, TxPrefix},
+    replay::TxFiles,
+    RoundResults, TestrpcError,
+};
+use crate::config::RoundTemplate;
+use crate::{jrpc, rng};
+use tokio_util::sync::CancellationToken;
+
+/// Types generated from the protos in `proto/`
+pub mod pb {
+    tonic::include_proto!("testrpc");
+
+    pub mod health {
+        tonic::include_proto!("grpc.health.v1");
+    }
+}
+
+/// Client-streaming method of the default tx-submission service
+const DEFAULT_METHOD: &str = "/testrpc.TxSubmission/SubmitTxs";
+
+/// Arguments for the gRPC adapter
+#[derive(Debug, Clone)]
+pub struct GrpcArgs {
+    /// Static list of the endpoints
+    pub endpoints: Vec<String>,
+    /// Path of the client-streaming method receiving `pb::Tx` and returning `pb::SubmitSummary`
+    pub method: PathAndQuery,
+    /// Service name sent in the health checks, empty for the overall health of the server
+    pub health_service: String,
+    /// Generator of the transactions, see `PayloadGenerators::from_args`
+    pub payload_generator: PayloadGenerators,
+    /// Prefix of the generated transactions, see `TxPrefix::from_args`
+    pub tx_prefix: TxPrefix,
+}
+
+impl TryFrom<HashMap<String, Value>> for GrpcArgs {
+    type Error = TestrpcError;
+
+    fn try_from(args: HashMap<String, Value>) -> Result<Self, Self::Error> {
+        let endpoints = match args.get("endpoints") {
+            Some(Value::Sequence(endpoints)) => endpoints
+                .iter()
+                .map(|endpoint| match endpoint {
+                    Value::String(endpoint) => Ok(endpoint.clone()),
+                    _ => Err(TestrpcError::MissingArgs(
+                        "endpoints must be a list of strings".to_string(),
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => Vec::new(),
+        };
+        let method = match args.get("method") {
+            Some(Value::String(method)) => method.clone(),
+            _ => DEFAULT_METHOD.to_string(),
+        };
+        let method = PathAndQuery::try_from(method.as_str())
+            .map_err(|e| TestrpcError::MissingArgs(format!("invalid method {method}: {e}")))?;
+        let health_service = match args.get("health_service") {
+            Some(Value::String(service)) => service.clone(),
+            _ => String::new(),
+        };
+
+        let payload_generator = PayloadGenerators::from_args(&args)?;
+        let tx_prefix = TxPrefix::from_args(&args)?;
+
+        Ok(GrpcArgs {
+            endpoints,
+            method,
+            health_service,
+            payload_generator,
+            tx_prefix,
+        })
+    }
+}
+
+pub struct GrpcAdapter {
+    args: GrpcArgs,
+    /// Channels by endpoint, connected lazily and reused across iterations
+    channels: Mutex<HashMap<String, Channel>>,
+    /// Tx files replayed by the templates with a `tx_source`
+    tx_files: TxFiles,
+    /// Txs of the rounds broadcasting identical txs
+    broadcasts: Broadcasts<pb::Tx>,
+    /// The txs aren't streamed in a dry run
+    dry_run: Option<jrpc::DryRun>,
+}
+
+impl GrpcAdapter {
+    pub fn new(args: GrpcArgs, dry_run: Option<jrpc::DryRun>) -> Self {
+        GrpcAdapter {
+            args,
+            channels: Mutex::new(HashMap::new()),
+            tx_files: TxFiles::default(),
+            broadcasts: Broadcasts::default(),
+            dry_run,
+        }
+    }
+
+    /// Transactions of a request, replayed from its tx source or generated
+    fn txs(
+        &self,
+        iteration: u32,
+        req_id: u64,
+        template: &RoundTemplate,
+    ) -> Result<Vec<pb::Tx>, TestrpcError> {
+        if let Some(source) = &template.tx_source {
+            return Ok(self
+                .tx_files
+                .take(source, template.txs)?
+                .into_iter()
+                .map(|data| pb::Tx { data })
+                .collect());
+        }
+        let rng = template.payload_rng(rng::seed(), iteration, req_id);
+        let mut generator = self
+            .args
+            .tx_prefix
+            .prefixed(self.args.payload_generator.for_request(rng));
+        Ok(template
+            .tx_sizes(iteration, req_id)
+            .into_iter()
+            .enumerate()
+            .map(|(i, tx_size)| pb::Tx {
+                data: generator.generate(i, tx_size),
+            })
+            .collect())
+    }
+
+    /// Returns the channel to the endpoint, creating it on first use
+    fn channel(&self, rpc_url: &str) -> Result<Channel, TestrpcError> {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(channel) = channels.get(rpc_url) {
+            return Ok(channel.clone());
+        }
+        let channel = Endpoint::from_shared(jrpc::with_scheme(rpc_url))
+            .map_err(|e| TestrpcError::RpcError(format!("Invalid endpoint {rpc_url}: {e}")))?
+            .connect_lazy();
+        channels.insert(rpc_url.to_string(), channel.clone());
+        Ok(channel)
+    }
+}
+
+impl TryFrom<HashMap<String, Value>> for GrpcAdapter {
+    type Error = TestrpcError;
+
+    fn try_from(args: HashMap<String, Value>) -> Result<Self, Self::Error> {
+        Ok(GrpcAdapter::new(GrpcArgs::try_from(args)?, None))
+    }
+}
+
+impl Adapter for GrpcAdapter {
+    async fn load_endpoints(
+        &self,
+        args: HashMap<String, Value>,
+    ) -> Result<Vec<String>, TestrpcError> {
+        let GrpcArgs { endpoints, .. } = GrpcArgs::try_from(args)?;
+        if endpoints.is_empty() {
+            return Err(TestrpcError::LoadEndpointsError(
+                "No endpoints declared in args.endpoints".to_string(),
+            ));
+        }
+        Ok(endpoints)
+    }
+
+    async fn ping_endpoint(
+        &self,
+        rpc_url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<bool, TestrpcError> {
+        if self.dry_run.is_some() {
+            return Ok(true);
+        }
+        let mut client = pb::health::health_client::HealthClient::new(self.channel(rpc_url)?);
+        let mut request = tonic::Request::new(pb::health::HealthCheckRequest {
+            service: self.args.health_service.clone(),
+        });
+        if let Some(timeout) = timeout {
+            request.set_timeout(timeout);
+        }
+        let status = client
+            .check(request)
+            .await
+            .map_err(|e| TestrpcError::RpcError(format!("Health check failed: {e}")))?
+            .into_inner()
+            .status();
+        if status != pb::health::health_check_response::ServingStatus::Serving {
+            return Err(TestrpcError::RpcError(format!(
+                "{rpc_url} is not serving: {status:?}"
+            )));
+        }
+        Ok(true)
+    }
+
+    fn validate_template(&self, template: &RoundTemplate) -> Result<(), TestrpcError> {
+        self.args.tx_prefix.validate(template)
+    }
+
+    async fn send_txs(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        iteration: u32,
+        template: &RoundTemplate,
+        timeout: Option<Duration>,
+        cancel: &CancellationToken,
+    ) -> Result<RoundResults, TestrpcError> {
+        if cancel.is_cancelled() {
+            return Ok(RoundResults::default());
+        }
+        let num_txs = template.txs;
+        let txs = self
+            .broadcasts
+            .txs(template, || self.txs(iteration, req_id, template))?;
+        let sizes = txs.iter().map(|tx| tx.data.len()).collect::<Vec<_>>();
+        let mut results = RoundResults::default();
+        if let Some(dry_run) = self.dry_run {
+            if dry_run == jrpc::DryRun::Realistic {
+                // encode the txs as they would be streamed, only the write is skipped
+                let start = Instant::now();
+                let encoded = txs.iter().map(|tx| tx.encode_to_vec().len()).sum::<usize>();
+                results.latencies.push(start.elapsed());
+                tracing::info!(
+                    "Dry run, encoded {} txs ({} bytes) without streaming them to {}",
+                    num_txs,
+                    encoded,
+                    rpc_url
+                );
+            } else {
+                tracing::info!("Dry run, not streaming {} txs to {}", num_txs, rpc_url);
+            }
+            results.sent = num_txs;
+            results.bytes_sent = sizes.iter().sum();
+            return Ok(results);
+        }
+
+        let mut request = tonic::Request::new(tokio_stream::iter(txs));
+        if let Some(timeout) = timeout {
+            request.set_timeout(timeout);
+        }
+
+        let start = Instant::now();
+        let mut grpc = tonic::client::Grpc::new(self.channel(rpc_url)?);
+        let stream = async {
+            match grpc.ready().await {
+                Ok(()) => grpc
+                    .client_streaming(
+                        request,
+                        self.args.method.clone(),
+                        ProstCodec::<pb::Tx, pb::SubmitSummary>::default(),
+                    )
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(format!("Failed to connect: {e}")),
+            }
+        };
+        // the summary comes at the end of the stream, an interrupted stream fails all its txs
+        let response = tokio::select! {
+            response = stream => response,
+            _ = cancel.cancelled() => Err("cancelled".to_string()),
+        };
+        match response {
+            Ok(response) => {
+                // the summary only counts the txs, the first ones are assumed to be accepted
+                let accepted = (response.into_inner().accepted as usize).min(num_txs);
+                results.sent = accepted;
+                results.bytes_sent = sizes[..accepted].iter().sum();
+                results.failed = num_txs - accepted;
+                results.bytes_failed = sizes[accepted..].iter().sum();
+                results.latencies.push(start.elapsed());
+            }
+            Err(e) => {
+                tracing::warn!("Failed to stream {} txs to {}: {}", num_txs, rpc_url, e);
+                results.failed = num_txs;
+                results.bytes_failed = sizes.iter().sum();
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        let args = GrpcArgs::try_from(HashMap::new()).unwrap();
+        assert_eq!(args.method.as_str(), DEFAULT_METHOD);
+        assert!(args.endpoints.is_empty());
+
+        let args = HashMap::from([("method".to_string(), Value::from("not a path"))]);
+        assert!(GrpcArgs::try_from(args).is_err());
+    }
+}
@@ -1,54 +1,453 @@
 /// Hotshot implementation of the adapter
 use crate::adapters::Adapter;
-use libp2p::Multiaddr;
-use rand::Rng as _;
+use base64::Engine as _;
+use libp2p::{multiaddr::Protocol, Multiaddr};
+use rand::{rngs::StdRng, Rng as _, SeedableRng as _};
+use serde::Deserialize;
 use serde_yaml::Value;
 use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
 
-use crate::common::{RoundResults, TestrpcError};
-use crate::jrpc;
+use crate::common::{
+    self,
+    broadcast::Broadcasts,
+    burst,
+    payload::{self, PayloadGenerator as _, PayloadGenerators, TxPrefix},
+    replay::TxFiles,
+    RoundResults, TestrpcError,
+};
+use crate::config::RoundTemplate;
+use crate::{jrpc, rng};
 
+/// Default JSON-RPC method receiving the transactions
 const RPC_METHOD: &str = "send_txs";
+/// Default JSON-RPC method returning the status of a node
+const STATUS_METHOD: &str = "status";
+/// Delay before the first retry of a failed request, doubled on each retry
+const SEND_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Fraction of the target rate below which the achieved rate is reported
+const RATE_TOLERANCE: f64 = 0.9;
+/// Maximum number of leading bytes of a pooled payload replaced with its counter
+const POOL_PREFIX_SIZE: usize = 8;
+
+/// Encoding of the transactions in the requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxEncoding {
+    #[default]
+    Hex,
+    /// Standard base64, with padding
+    Base64,
+}
+
+impl TxEncoding {
+    pub fn encode(&self, tx: &[u8]) -> String {
+        match self {
+            TxEncoding::Hex => hex::encode(tx),
+            TxEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(tx),
+        }
+    }
+
+    /// Size in bytes of an encoded transaction
+    pub fn decoded_len(&self, tx: &str) -> usize {
+        match self {
+            TxEncoding::Hex => tx.len() / 2,
+            TxEncoding::Base64 => {
+                let padding = tx.bytes().rev().take_while(|b| *b == b'=').count();
+                tx.len() / 4 * 3 - padding
+            }
+        }
+    }
+}
 
 /// Arguments for the Hotshot adapter
+#[derive(Debug, Clone)]
 pub struct HotshotArgs {
-    /// Coordinator URL to use for fetching the RPC endpoints
-    pub coordinator_url: String,
+    /// Coordinator URLs to fetch the RPC endpoints from, tried in order until one answers.
+    /// `coordinator_url` is a shorthand for a single coordinator.
+    pub coordinator_urls: Vec<String>,
     /// RPC port to use for sending transactions
     pub rpc_port: u16,
+    /// Send the transactions as a JSON-RPC batch, one request per transaction
+    pub batch: bool,
+    /// Use HTTP/2 with prior knowledge, see `jrpc::ClientOptions::http2`
+    pub http2: bool,
+    /// Path to a PEM root certificate to trust for https endpoints
+    pub ca_cert: Option<String>,
+    /// Headers attached to every request, e.g. for authenticated endpoints
+    pub headers: HashMap<String, String>,
+    /// Number of retries of a request failing with a network error
+    pub send_retries: usize,
+    /// Maximum number of requests in flight to each node, unbounded if None
+    pub max_in_flight: Option<usize>,
+    /// Salt mixed into the request ids, random per run if None so that concurrent
+    /// runs against the same nodes don't reuse the same ids
+    pub req_id_salt: Option<u64>,
+    /// Seed of the random payloads, making them reproducible across runs
+    pub payload_seed: Option<u64>,
+    /// Generator of the transactions, the random ones being reproducible if `payload_seed` is set
+    pub payload_generator: PayloadGenerators,
+    /// Prefix of the generated transactions, see `TxPrefix::from_args`.
+    /// The pooled payloads start with it, followed by their counter.
+    pub tx_prefix: TxPrefix,
+    /// Encoding of the transactions in the requests
+    pub tx_encoding: TxEncoding,
+    /// Number of random payloads generated once per tx size and cycled through, instead of
+    /// generating each payload. Their leading bytes are replaced with a counter to keep them unique.
+    pub payload_pool_size: Option<usize>,
+    /// JSON-RPC method receiving the transactions, also called empty to ping the nodes
+    pub rpc_method: String,
+    /// JSON-RPC method returning the status of a node, an object with `height` (or `block_height`)
+    /// and `mempool_size`
+    pub status_method: String,
+    /// Path of the JSON-RPC endpoint on the nodes, see `jrpc::ClientOptions::rpc_path`
+    pub rpc_path: Option<String>,
 }
 
 impl TryFrom<HashMap<String, Value>> for HotshotArgs {
     type Error = TestrpcError;
 
     fn try_from(args: HashMap<String, Value>) -> Result<Self, Self::Error> {
-        let coordinator_url = match args.get("coordinator_url") {
-            Some(Value::String(coordinator_url)) => coordinator_url.clone(),
-            _ => return Err(TestrpcError::MissingArgs("coordinator_url".to_string())),
+        let mut coordinator_urls = match args.get("coordinator_url") {
+            Some(Value::String(coordinator_url)) => vec![coordinator_url.clone()],
+            _ => Vec::new(),
         };
+        if let Some(Value::Sequence(urls)) = args.get("coordinator_urls") {
+            for url in urls {
+                match url {
+                    Value::String(url) => coordinator_urls.push(url.clone()),
+                    _ => {
+                        return Err(TestrpcError::MissingArgs(
+                            "coordinator_urls must be a list of strings".to_string(),
+                        ))
+                    }
+                }
+            }
+        }
         let rpc_port = match args.get("rpc_port") {
             Some(Value::Number(port)) if port.is_u64() => port.as_u64().unwrap() as u16,
             _ => 5000,
         };
+        let batch = matches!(args.get("batch"), Some(Value::Bool(true)));
+        let jrpc::ClientOptions {
+            http2,
+            ca_cert,
+            headers,
+            rpc_path,
+            ..
+        } = jrpc::ClientOptions::from_args(&args)?;
+
+        let send_retries = match args.get("send_retries") {
+            Some(Value::Number(retries)) if retries.is_u64() => retries.as_u64().unwrap() as usize,
+            _ => 0,
+        };
+
+        let max_in_flight = match args.get("max_in_flight") {
+            Some(Value::Number(max)) if max.as_u64().is_some_and(|max| max > 0) => {
+                max.as_u64().map(|max| max as usize)
+            }
+            Some(_) => {
+                return Err(TestrpcError::MissingArgs(
+                    "max_in_flight must be a positive integer".to_string(),
+                ))
+            }
+            None => None,
+        };
+
+        let req_id_salt = match args.get("req_id_salt") {
+            Some(Value::Number(salt)) if salt.is_u64() => salt.as_u64(),
+            _ => None,
+        };
+
+        let payload_seed = match args.get("payload_seed") {
+            Some(Value::Number(seed)) if seed.is_u64() => seed.as_u64(),
+            _ => None,
+        };
+        let payload_generator = PayloadGenerators::from_args(&args)?;
+        let tx_prefix = TxPrefix::from_args(&args)?;
+        let tx_encoding = match args.get("tx_encoding") {
+            Some(encoding) => serde_yaml::from_value(encoding.clone()).map_err(|_| {
+                TestrpcError::MissingArgs("tx_encoding must be one of hex, base64".to_string())
+            })?,
+            None => TxEncoding::default(),
+        };
+        let payload_pool_size = match args.get("payload_pool_size") {
+            Some(Value::Number(size)) if size.as_u64().is_some_and(|size| size > 0) => {
+                size.as_u64().map(|size| size as usize)
+            }
+            Some(_) => {
+                return Err(TestrpcError::MissingArgs(
+                    "payload_pool_size must be a positive integer".to_string(),
+                ))
+            }
+            None => None,
+        };
+        let rpc_method = match args.get("rpc_method") {
+            Some(Value::String(method)) => method.clone(),
+            _ => RPC_METHOD.to_string(),
+        };
+        let status_method = match args.get("status_method") {
+            Some(Value::String(method)) => method.clone(),
+            _ => STATUS_METHOD.to_string(),
+        };
 
         Ok(HotshotArgs {
-            coordinator_url,
+            coordinator_urls,
             rpc_port,
+            batch,
+            http2,
+            ca_cert,
+            headers,
+            send_retries,
+            max_in_flight,
+            req_id_salt,
+            payload_seed,
+            payload_generator,
+            tx_prefix,
+            tx_encoding,
+            payload_pool_size,
+            rpc_method,
+            status_method,
+            rpc_path,
         })
     }
 }
 
-pub struct HotshotAdapter;
+pub struct HotshotAdapter {
+    args: HotshotArgs,
+    client: jrpc::Client,
+    /// Salt of the request ids, see `HotshotArgs::req_id_salt`
+    req_id_salt: u64,
+    /// Pools of hex encoded payloads by tx size, see `HotshotArgs::payload_pool_size`
+    payload_pools: Mutex<HashMap<usize, Arc<Vec<Vec<u8>>>>>,
+    /// Counter of the pooled payloads, starting at the salt so that it differs across runs
+    payload_counter: AtomicU64,
+    /// Tx files replayed by the templates with a `tx_source`
+    tx_files: TxFiles,
+    /// Permits of the requests in flight by node, see `HotshotArgs::max_in_flight`
+    in_flight: Mutex<HashMap<String, Arc<Semaphore>>>,
+    /// Txs of the rounds broadcasting identical txs
+    broadcasts: Broadcasts<String>,
+}
 
 impl HotshotAdapter {
-    pub fn new() -> Self {
-        HotshotAdapter {}
+    pub fn new(mut args: HotshotArgs, dry_run: Option<jrpc::DryRun>) -> Result<Self, TestrpcError> {
+        // the payloads of a seeded run are reproducible too
+        args.payload_seed = args.payload_seed.or_else(rng::seed);
+        let client = jrpc::Client::new(&jrpc::ClientOptions {
+            http2: args.http2,
+            ca_cert: args.ca_cert.clone(),
+            headers: args.headers.clone(),
+            rpc_path: args.rpc_path.clone(),
+            dry_run,
+        })?;
+        let req_id_salt = args
+            .req_id_salt
+            .unwrap_or_else(|| rng::with_rng(|rng| rng.random::<u64>()));
+        Ok(HotshotAdapter {
+            args,
+            client,
+            req_id_salt,
+            payload_pools: Mutex::new(HashMap::new()),
+            payload_counter: AtomicU64::new(req_id_salt),
+            tx_files: TxFiles::default(),
+            in_flight: Mutex::new(HashMap::new()),
+            broadcasts: Broadcasts::default(),
+        })
+    }
+
+    /// Pool of `size` random payloads of `tx_size` bytes, generated on first use.
+    /// It only depends on the payload seed and the tx size if the seed is set.
+    fn payload_pool(&self, size: usize, tx_size: usize) -> Arc<Vec<Vec<u8>>> {
+        let mut pools = self.payload_pools.lock().unwrap();
+        pools
+            .entry(tx_size)
+            .or_insert_with(|| {
+                let rng = match self.args.payload_seed {
+                    Some(seed) => StdRng::seed_from_u64(seed ^ tx_size as u64),
+                    None => rng::with_rng(|rng| StdRng::seed_from_u64(rng.random())),
+                };
+                let mut random = payload::Random(Some(rng));
+                Arc::new((0..size).map(|i| random.generate(i, tx_size)).collect())
+            })
+            .clone()
+    }
+
+    /// Take the transactions of a request from the payload pool, their leading bytes
+    /// being replaced with the payload counter. Variable size transactions are truncated
+    /// from a pool of the maximum size.
+    fn pooled_payloads(
+        &self,
+        pool_size: usize,
+        template: &RoundTemplate,
+        tx_sizes: &[usize],
+    ) -> Vec<String> {
+        let max_tx_size = tx_sizes.iter().copied().max().unwrap_or(template.tx_size);
+        let pool = self.payload_pool(pool_size, template.tx_size_max.unwrap_or(max_tx_size));
+        let first = self
+            .payload_counter
+            .fetch_add(template.txs as u64, Ordering::Relaxed);
+        (0..template.txs as u64)
+            .zip(tx_sizes)
+            .map(|(i, tx_size)| {
+                let counter = first.wrapping_add(i);
+                let mut payload = pool[(counter % pool.len() as u64) as usize].clone();
+                payload.truncate(*tx_size);
+                let tx_prefix = &self.args.tx_prefix.0[..self.args.tx_prefix.len().min(*tx_size)];
+                payload[..tx_prefix.len()].copy_from_slice(tx_prefix);
+                let prefix_size = (tx_size - tx_prefix.len()).min(POOL_PREFIX_SIZE);
+                payload[tx_prefix.len()..tx_prefix.len() + prefix_size]
+                    .copy_from_slice(&counter.to_be_bytes()[POOL_PREFIX_SIZE - prefix_size..]);
+                self.args.tx_encoding.encode(&payload)
+            })
+            .collect()
+    }
+
+    /// Generate the transactions of a request, encoded with `tx_encoding`.
+    /// With a payload seed, the random payloads only depend on the seed, the iteration and the request id.
+    fn payloads(&self, iteration: u32, req_id: u64, template: &RoundTemplate) -> Vec<String> {
+        let tx_sizes = template.tx_sizes(iteration, req_id);
+        // the counters of the pooled payloads would differ across the rpcs of a broadcast
+        if let (true, Some(pool_size), None) = (
+            self.args.payload_generator.is_random(),
+            self.args.payload_pool_size,
+            template.broadcast_seed,
+        ) {
+            return self.pooled_payloads(pool_size, template, &tx_sizes);
+        }
+        let rng = template.payload_rng(self.args.payload_seed, iteration, req_id);
+        let mut generator = self
+            .args
+            .tx_prefix
+            .prefixed(self.args.payload_generator.for_request(rng));
+        tx_sizes
+            .into_iter()
+            .enumerate()
+            .map(|(i, tx_size)| {
+                self.args
+                    .tx_encoding
+                    .encode(&generator.generate(i, tx_size))
+            })
+            .collect()
+    }
+
+    /// Encoded transactions of a request, replayed from its tx source or generated.
+    /// The requests of a round broadcasting identical txs share the ones of its first request.
+    fn txs(
+        &self,
+        iteration: u32,
+        req_id: u64,
+        template: &RoundTemplate,
+    ) -> Result<Vec<String>, TestrpcError> {
+        self.broadcasts.txs(template, || match &template.tx_source {
+            Some(source) => Ok(self
+                .tx_files
+                .take(source, template.txs)?
+                .iter()
+                .map(|tx| self.args.tx_encoding.encode(tx))
+                .collect()),
+            None => Ok(self.payloads(iteration, req_id, template)),
+        })
+    }
+
+    /// Size in bytes of encoded transactions
+    fn txs_bytes(&self, txs: &[String]) -> usize {
+        txs.iter()
+            .map(|tx| self.args.tx_encoding.decoded_len(tx))
+            .sum()
     }
+
+    /// Mix the salt of the run into the request id
+    fn salted(&self, req_id: u64) -> u64 {
+        req_id.wrapping_add(self.req_id_salt)
+    }
+
+    /// Wait for a permit to send a request to the node if `max_in_flight` is set,
+    /// the request is in flight until the permit is dropped
+    async fn in_flight_permit(&self, rpc_url: &str) -> Option<OwnedSemaphorePermit> {
+        let max_in_flight = self.args.max_in_flight?;
+        let semaphore = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(rpc_url.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_in_flight)))
+            .clone();
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        tracing::info!(
+            "{} requests in flight to {}, waiting for one to complete",
+            max_in_flight,
+            rpc_url
+        );
+        // the semaphore is never closed
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// Send the transactions of `params`, retrying failed requests up to `send_retries` times.
+    /// Only network errors are retried, JSON-RPC errors (e.g. rejected txs) are returned right away.
+    async fn send(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        params: serde_json::Value,
+        timeout: Option<Duration>,
+    ) -> Result<jrpc::RpcResponse, TestrpcError> {
+        let _permit = self.in_flight_permit(rpc_url).await;
+        common::retry_if(
+            self.args.send_retries + 1,
+            SEND_RETRY_DELAY,
+            || {
+                Box::pin(self.client.send(
+                    rpc_url,
+                    req_id,
+                    &self.args.rpc_method,
+                    params.clone(),
+                    timeout,
+                ))
+            },
+            true,
+            is_transient,
+        )
+        .await
+    }
+
+    /// Send a JSON-RPC batch, retrying it as a whole on network errors, see `send`
+    async fn send_batch(
+        &self,
+        rpc_url: &str,
+        requests: Vec<jrpc::RpcRequest>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Result<jrpc::RpcResponse, TestrpcError>>, TestrpcError> {
+        let _permit = self.in_flight_permit(rpc_url).await;
+        common::retry_if(
+            self.args.send_retries + 1,
+            SEND_RETRY_DELAY,
+            || Box::pin(self.client.send_batch(rpc_url, requests.clone(), timeout)),
+            true,
+            is_transient,
+        )
+        .await
+    }
+}
+
+/// Whether the error may go away when retrying the request
+fn is_transient(e: &TestrpcError) -> bool {
+    matches!(e.cause(), TestrpcError::RequestError(_))
 }
 
-impl Default for HotshotAdapter {
-    fn default() -> Self {
-        HotshotAdapter::new()
+impl TryFrom<HashMap<String, Value>> for HotshotAdapter {
+    type Error = TestrpcError;
+
+    fn try_from(args: HashMap<String, Value>) -> Result<Self, Self::Error> {
+        HotshotAdapter::new(HotshotArgs::try_from(args)?, None)
     }
 }
 
@@ -58,25 +457,40 @@ impl Adapter for HotshotAdapter {
         args: HashMap<String, Value>,
     ) -> Result<Vec<String>, TestrpcError> {
         let HotshotArgs {
-            coordinator_url,
+            coordinator_urls,
             rpc_port,
+            rpc_path,
+            ..
         } = HotshotArgs::try_from(args)?;
-        tracing::info!("Using coordinator at: {}", coordinator_url.clone());
-        // Fetch the known libp2p nodes from the coordinator
-        let p2p_info_url = format!("http://{coordinator_url}/libp2p-info");
-        let resp = reqwest::get(p2p_info_url.as_str())
-            .await
-            .map_err(|e| TestrpcError::LoadEndpointsError(e.to_string()))?
-            .text()
-            .await
-            .map_err(|e| TestrpcError::LoadEndpointsError(e.to_string()))?;
-        let known_ips = parse_endpoints(resp.as_str())?;
+        if coordinator_urls.is_empty() {
+            return Err(TestrpcError::MissingArgs(
+                "coordinator_url or coordinator_urls".to_string(),
+            ));
+        }
+        let mut known_ips = Vec::new();
+        for coordinator_url in coordinator_urls.iter() {
+            match fetch_nodes(&self.client, coordinator_url).await {
+                Ok(ips) if !ips.is_empty() => {
+                    tracing::info!("Using coordinator at: {}", coordinator_url);
+                    known_ips = ips;
+                    break;
+                }
+                Ok(_) => tracing::warn!("Coordinator {} knows no nodes", coordinator_url),
+                Err(e) => tracing::warn!("Coordinator {} failed: {}", coordinator_url, e),
+            }
+        }
+        if known_ips.is_empty() {
+            return Err(TestrpcError::LoadEndpointsError(format!(
+                "All {} coordinators failed",
+                coordinator_urls.len()
+            )));
+        }
         // Print the known libp2p nodes
         tracing::info!("Known libp2p nodes: {:?}", known_ips);
 
         let rpc_urls = known_ips
             .iter()
-            .map(|ip| format!("http://{ip}:{rpc_port}"))
+            .map(|ip| jrpc::with_path(&format!("http://{ip}:{rpc_port}"), rpc_path.as_deref()))
             .collect::<Vec<_>>();
 
         if rpc_urls.is_empty() {
@@ -92,75 +506,636 @@ impl Adapter for HotshotAdapter {
         rpc_url: &str,
         timeout: Option<std::time::Duration>,
     ) -> Result<bool, crate::common::TestrpcError> {
-        let req_id = rand::rng().random::<u64>();
-        let _ = jrpc::send(rpc_url, req_id, RPC_METHOD, serde_json::json!({}), timeout).await?;
+        let req_id = rng::with_rng(|rng| rng.random::<u64>());
+        let _ = self
+            .client
+            .send(
+                rpc_url,
+                req_id,
+                &self.args.rpc_method,
+                serde_json::json!({}),
+                timeout,
+            )
+            .await?;
 
         Ok(true)
     }
 
+    async fn node_status(
+        &self,
+        rpc_url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<common::NodeStatus, TestrpcError> {
+        let req_id = rng::with_rng(|rng| rng.random::<u64>());
+        let response = self
+            .client
+            .send(
+                rpc_url,
+                req_id,
+                &self.args.status_method,
+                serde_json::json!({}),
+                timeout,
+            )
+            .await?;
+        serde_json::from_value(response.result().clone())
+            .map_err(|e| TestrpcError::RpcError(format!("Invalid status from {rpc_url}: {e}")))
+    }
+
+    fn sent_req_id(&self, req_id: u64) -> u64 {
+        self.salted(req_id)
+    }
+
+    fn validate_template(&self, template: &RoundTemplate) -> Result<(), TestrpcError> {
+        self.args.tx_prefix.validate(template)
+    }
+
     async fn send_txs(
         &self,
         rpc_url: &str,
         req_id: u64,
-        _iteration: u32,
-        num_txs: usize,
-        tx_size: usize,
+        iteration: u32,
+        template: &RoundTemplate,
         timeout: Option<std::time::Duration>,
+        cancel: &CancellationToken,
     ) -> Result<RoundResults, TestrpcError> {
-        let mut txs: Vec<String> = Vec::new();
-        for _ in 0..num_txs {
-            let mut transaction_bytes = vec![0u8; tx_size];
-            rand::rng().fill(&mut transaction_bytes[..]);
-            txs.push(hex::encode(transaction_bytes));
+        if cancel.is_cancelled() {
+            return Ok(RoundResults::default());
         }
-        let _ = jrpc::send(
-            rpc_url,
+        let txs = self.txs(iteration, req_id, template)?;
+        let delays = template.arrival_delays(iteration, req_id);
+        let req_id = self.salted(req_id);
+
+        if self.args.batch && delays.is_none() && template.rate.is_none() {
+            return Ok(send_txs_batch(self, rpc_url, req_id, txs, timeout).await);
+        }
+
+        if let Some(rate) = template.rate {
+            return Ok(send_txs_at_rate(self, rpc_url, req_id, txs, rate, timeout, cancel).await);
+        }
+
+        let mut results = RoundResults::default();
+        let Some(delays) = delays else {
+            let bytes = self.txs_bytes(&txs);
+            let response = self
+                .send(rpc_url, req_id, serde_json::json!({ "txs": txs }), timeout)
+                .await;
+            record_response(&mut results, rpc_url, response, template.txs, bytes);
+            return Ok(results);
+        };
+
+        // Send the transactions one by one, waiting for the arrival delays in between
+        let num_txs = txs.len();
+        for (i, tx) in txs.into_iter().enumerate() {
+            if i > 0 {
+                tokio::select! {
+                    _ = tokio::time::sleep(delays[i - 1]) => {}
+                    _ = cancel.cancelled() => {
+                        tracing::debug!("Cancelled, {} txs left unsent to {}", num_txs - i, rpc_url);
+                        break;
+                    }
+                }
+            }
+            let bytes = self.args.tx_encoding.decoded_len(&tx);
+            let response = self
+                .send(
+                    rpc_url,
+                    req_id.wrapping_add(i as u64),
+                    serde_json::json!({ "txs": [tx] }),
+                    timeout,
+                )
+                .await;
+            record_response(&mut results, rpc_url, response, 1, bytes);
+        }
+        Ok(results)
+    }
+}
+
+/// Record the outcome of a request carrying `num_txs` transactions of `bytes` bytes in total
+fn record_response(
+    results: &mut RoundResults,
+    rpc_url: &str,
+    response: Result<jrpc::RpcResponse, TestrpcError>,
+    num_txs: usize,
+    bytes: usize,
+) {
+    match response {
+        Ok(response) => {
+            results.sent += num_txs;
+            results.bytes_sent += bytes;
+            results.latencies.push(response.elapsed());
+        }
+        Err(TestrpcError::RequestFailed {
+            endpoint,
             req_id,
-            RPC_METHOD,
-            serde_json::json!({ "txs": txs }),
-            timeout,
-        )
-        .await?;
+            method,
+            cause,
+        }) => {
+            tracing::warn!(
+                endpoint,
+                req_id,
+                method,
+                "Request {} with {} txs to {} failed: {}",
+                req_id,
+                num_txs,
+                endpoint,
+                cause
+            );
+            results.failed += num_txs;
+            results.bytes_failed += bytes;
+        }
+        Err(e) => {
+            tracing::warn!("Failed to send {} txs to {}: {}", num_txs, rpc_url, e);
+            results.failed += num_txs;
+            results.bytes_failed += bytes;
+        }
+    }
+}
 
-        Ok(RoundResults {
-            sent: num_txs,
-            failed: 0,
+/// Send the transactions as a JSON-RPC batch, counting each failed request in the batch
+async fn send_txs_batch(
+    adapter: &HotshotAdapter,
+    rpc_url: &str,
+    req_id: u64,
+    txs: Vec<String>,
+    timeout: Option<std::time::Duration>,
+) -> RoundResults {
+    let num_txs = txs.len();
+    let sizes = txs
+        .iter()
+        .map(|tx| adapter.args.tx_encoding.decoded_len(tx))
+        .collect::<Vec<_>>();
+    let requests = txs
+        .into_iter()
+        .enumerate()
+        .map(|(i, tx)| {
+            jrpc::RpcRequest::new(
+                req_id.wrapping_add(i as u64),
+                &adapter.args.rpc_method,
+                serde_json::json!({ "txs": [tx] }),
+            )
         })
+        .collect::<Vec<_>>();
+
+    let mut results = RoundResults::default();
+    let responses = match adapter.send_batch(rpc_url, requests, timeout).await {
+        Ok(responses) => responses,
+        Err(e) => {
+            record_response(&mut results, rpc_url, Err(e), num_txs, sizes.iter().sum());
+            return results;
+        }
+    };
+    // All the responses of a batch share the same latency, record it once
+    if let Some(Ok(response)) = responses.iter().find(|r| r.is_ok()) {
+        results.latencies.push(response.elapsed());
+    }
+    for (response, size) in responses.into_iter().zip(sizes) {
+        match response {
+            Ok(_) => {
+                results.sent += 1;
+                results.bytes_sent += size;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to send tx: {}", e);
+                results.failed += 1;
+                results.bytes_failed += size;
+            }
+        }
     }
+    results
 }
 
-fn parse_endpoints(endpoints: &str) -> Result<Vec<String>, TestrpcError> {
-    let endpoints = endpoints
-        .split('\n')
-        .map(|s| {
-            s.parse::<Multiaddr>()
-                .map_err(|e| TestrpcError::LoadEndpointsError(e.to_string()))
-        })
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter();
+/// Send the transactions at the given rate (txs per second), one request per burst
+async fn send_txs_at_rate(
+    adapter: &HotshotAdapter,
+    rpc_url: &str,
+    req_id: u64,
+    txs: Vec<String>,
+    rate: u32,
+    timeout: Option<std::time::Duration>,
+    cancel: &CancellationToken,
+) -> RoundResults {
+    let num_txs = txs.len();
+    let start = Instant::now();
+    let results = burst::send(num_txs, rate, cancel, |range| {
+        let req_id = req_id.wrapping_add(range.start as u64);
+        let burst = &txs[range];
+        async move {
+            let mut results = RoundResults::default();
+            let response = adapter
+                .send(
+                    rpc_url,
+                    req_id,
+                    serde_json::json!({ "txs": burst }),
+                    timeout,
+                )
+                .await;
+            record_response(
+                &mut results,
+                rpc_url,
+                response,
+                burst.len(),
+                adapter.txs_bytes(burst),
+            );
+            results
+        }
+    })
+    .await;
+    let achieved = num_txs as f64 / start.elapsed().as_secs_f64();
+    if !cancel.is_cancelled() && achieved < rate as f64 * RATE_TOLERANCE {
+        tracing::warn!(
+            "Sent {} txs to {} at {:.1} tx/s, below the target of {} tx/s",
+            num_txs,
+            rpc_url,
+            achieved,
+            rate
+        );
+    }
+    results
+}
 
+/// Fetch the known libp2p nodes from the coordinator with the client of the adapter,
+/// without duplicates
+async fn fetch_nodes(
+    client: &jrpc::Client,
+    coordinator_url: &str,
+) -> Result<Vec<String>, TestrpcError> {
+    let p2p_info_url = format!("{}/libp2p-info", jrpc::with_scheme(coordinator_url));
+    let resp = client
+        .get(&p2p_info_url, None)
+        .await
+        .map_err(|e| TestrpcError::LoadEndpointsError(e.to_string()))?;
+    let mut ips = parse_endpoints(resp.as_str())?;
+    let mut seen = std::collections::HashSet::new();
+    ips.retain(|ip| seen.insert(ip.clone()));
+    Ok(ips)
+}
+
+/// Parse the multiaddrs returned by the coordinator, one per line, into the hosts of the nodes.
+/// Malformed lines are skipped with a warning, it fails only if no valid multiaddr is left.
+fn parse_endpoints(endpoints: &str) -> Result<Vec<String>, TestrpcError> {
     let addrs = endpoints
-        .map(|addr| {
-            let components = addr.iter().collect::<Vec<_>>();
-            if components.len() < 2 {
-                return addr.to_string();
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match line.parse::<Multiaddr>() {
+            Ok(addr) => {
+                let host = multiaddr_host(&addr);
+                if host.is_none() {
+                    tracing::warn!("Skipping multiaddr without ip or dns host {}", addr);
+                }
+                host
             }
-            let ip_addr = components[0].to_string();
-            if ip_addr.len() < 6 {
-                return addr.to_string();
+            Err(e) => {
+                tracing::warn!("Skipping malformed multiaddr {:?}: {}", line, e);
+                None
             }
-            // "/ip4/*.*.*.*" -> "*.*.*.*"
-            ip_addr[5..].to_string()
         })
-        .filter(|s| !s.is_empty())
         .collect::<Vec<_>>();
-
+    if addrs.is_empty() {
+        return Err(TestrpcError::LoadEndpointsError(
+            "No valid multiaddr returned by the coordinator".to_string(),
+        ));
+    }
     Ok(addrs)
 }
 
+/// Host of the multiaddr as it goes in a URL, IPv6 addresses are bracketed
+fn multiaddr_host(addr: &Multiaddr) -> Option<String> {
+    addr.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(ip.to_string()),
+        Protocol::Ip6(ip) => Some(format!("[{ip}]")),
+        Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name) => Some(name.to_string()),
+        _ => None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::TxSource;
+
+    #[tokio::test]
+    async fn test_send_txs_batch() {
+        let args = HashMap::from([("batch".to_string(), Value::Bool(true))]);
+        let adapter = HotshotAdapter::new(
+            HotshotArgs::try_from(args).unwrap(),
+            Some(jrpc::DryRun::Noop),
+        )
+        .unwrap();
+        let template = RoundTemplate {
+            txs: 5,
+            tx_size: 10,
+            ..Default::default()
+        };
+        let results = adapter
+            .send_txs(
+                "http://localhost:5000",
+                0,
+                0,
+                &template,
+                None,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.sent, 5);
+        assert_eq!(results.failed, 0);
+        assert_eq!(results.bytes_sent, 50);
+        assert_eq!(results.latencies.len(), 1);
+    }
+
+    #[test]
+    fn test_payloads() {
+        let template = RoundTemplate {
+            txs: 2,
+            tx_size: 3,
+            ..Default::default()
+        };
+        let seeded = || {
+            HotshotAdapter::try_from(HashMap::from([(
+                "payload_seed".to_string(),
+                Value::from(42),
+            )]))
+            .unwrap()
+        };
+        // reproducible across adapters, different across requests
+        assert_eq!(
+            seeded().payloads(1, 1, &template),
+            seeded().payloads(1, 1, &template)
+        );
+        assert_ne!(
+            seeded().payloads(1, 1, &template),
+            seeded().payloads(2, 1, &template)
+        );
+
+        let adapter = |mode: &str| {
+            HotshotAdapter::try_from(HashMap::from([(
+                "payload_mode".to_string(),
+                Value::from(mode),
+            )]))
+        };
+        assert_eq!(
+            adapter("incremental").unwrap().payloads(1, 1, &template),
+            vec!["000102", "010203"]
+        );
+        assert_eq!(
+            adapter("zero").unwrap().payloads(1, 1, &template),
+            vec!["000000", "000000"]
+        );
+        assert!(adapter("other").is_err());
+
+        // the requests of a broadcast share their payloads, even pooled ones
+        let pooled = HotshotAdapter::try_from(HashMap::from([(
+            "payload_pool_size".to_string(),
+            Value::from(2),
+        )]))
+        .unwrap();
+        let template = RoundTemplate {
+            broadcast_seed: Some(7),
+            ..template
+        };
+        assert_eq!(
+            pooled.payloads(1, 1, &template),
+            pooled.payloads(1, 2, &template)
+        );
+    }
+
+    #[test]
+    fn test_broadcast_txs() {
+        let path = std::env::temp_dir().join(format!("testrpc-broadcast-{}", std::process::id()));
+        std::fs::write(&path, "01\n02\n03\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+        let template = RoundTemplate {
+            txs: 2,
+            broadcast_seed: Some(7),
+            broadcast_requests: 2,
+            ..Default::default()
+        };
+
+        // the tx file and the file generator are only read by the first request of the round
+        let adapter = HotshotAdapter::try_from(HashMap::new()).unwrap();
+        let replayed = RoundTemplate {
+            tx_source: Some(TxSource::File { path: path.clone() }),
+            ..template.clone()
+        };
+        let txs = adapter.txs(1, 1, &replayed).unwrap();
+        assert_eq!(txs, vec!["01", "02"]);
+        assert_eq!(adapter.txs(1, 2, &replayed).unwrap(), txs);
+        assert_eq!(adapter.txs(2, 3, &replayed).unwrap(), vec!["03", "01"]);
+
+        let file = serde_yaml::from_str(&format!("file: {path}")).unwrap();
+        let adapter =
+            HotshotAdapter::try_from(HashMap::from([("payload_generator".to_string(), file)]))
+                .unwrap();
+        let template = RoundTemplate {
+            tx_size: 1,
+            ..template
+        };
+        let txs = adapter.txs(1, 1, &template).unwrap();
+        assert_eq!(txs, vec!["01", "02"]);
+        assert_eq!(adapter.txs(1, 2, &template).unwrap(), txs);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_pooled_payloads() {
+        let template = RoundTemplate {
+            txs: 5,
+            tx_size: 10,
+            ..Default::default()
+        };
+        let adapter = HotshotAdapter::try_from(HashMap::from([
+            ("payload_pool_size".to_string(), Value::from(2)),
+            ("req_id_salt".to_string(), Value::from(0)),
+        ]))
+        .unwrap();
+        let payloads = adapter.payloads(1, 1, &template);
+        assert_eq!(payloads.len(), 5);
+        assert!(payloads.iter().all(|payload| payload.len() == 20));
+        // unique counter prefix, the rest cycles through the pool
+        assert!(payloads[0].starts_with("0000000000000000"));
+        assert!(payloads[4].starts_with("0000000000000004"));
+        assert_eq!(payloads[0][16..], payloads[2][16..]);
+        assert_eq!(payloads[1][16..], payloads[3][16..]);
+        assert_ne!(payloads[0], payloads[2]);
+        // the counter goes on across requests
+        assert!(adapter.payloads(1, 2, &template)[0].starts_with("0000000000000005"));
+
+        let args = HashMap::from([("payload_pool_size".to_string(), Value::from(0))]);
+        assert!(HotshotAdapter::try_from(args).is_err());
+
+        // variable size payloads are truncated from the pool
+        let template = RoundTemplate {
+            txs: 20,
+            tx_size: 4,
+            tx_size_max: Some(16),
+            ..Default::default()
+        };
+        let payloads = adapter.payloads(1, 3, &template);
+        assert!(payloads
+            .iter()
+            .all(|payload| (8..=32).contains(&payload.len())));
+
+        // the tx prefix comes before the counter
+        let template = RoundTemplate {
+            txs: 1,
+            tx_size: 12,
+            ..Default::default()
+        };
+        let adapter = HotshotAdapter::try_from(HashMap::from([
+            ("payload_pool_size".to_string(), Value::from(2)),
+            ("req_id_salt".to_string(), Value::from(0)),
+            ("tx_prefix".to_string(), Value::from("cafe")),
+        ]))
+        .unwrap();
+        let payloads = adapter.payloads(1, 1, &template);
+        assert_eq!(payloads[0].len(), 24);
+        assert!(payloads[0].starts_with("cafe0000000000000000"));
+    }
+
+    #[test]
+    fn test_tx_encoding() {
+        let tx = b"testrpc";
+        assert_eq!(TxEncoding::Hex.encode(tx), "74657374727063");
+        let encoded = TxEncoding::Base64.encode(tx);
+        assert_eq!(encoded, "dGVzdHJwYw==");
+        assert_eq!(
+            base64::engine::general_purpose::STANDARD
+                .decode(&encoded)
+                .unwrap(),
+            tx
+        );
+        for len in 0..5 {
+            let tx = vec![0u8; len];
+            assert_eq!(
+                TxEncoding::Hex.decoded_len(&TxEncoding::Hex.encode(&tx)),
+                len
+            );
+            assert_eq!(
+                TxEncoding::Base64.decoded_len(&TxEncoding::Base64.encode(&tx)),
+                len
+            );
+        }
+
+        let args = HashMap::from([("tx_encoding".to_string(), Value::from("base64"))]);
+        let adapter = HotshotAdapter::try_from(args).unwrap();
+        let template = RoundTemplate {
+            txs: 1,
+            tx_size: 3,
+            ..Default::default()
+        };
+        assert_eq!(adapter.payloads(1, 1, &template)[0].len(), 4);
+        let args = HashMap::from([("tx_encoding".to_string(), Value::from("base32"))]);
+        assert!(HotshotAdapter::try_from(args).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_permit() {
+        let adapter = HotshotAdapter::try_from(HashMap::new()).unwrap();
+        assert!(adapter.in_flight_permit("node").await.is_none());
+
+        let args = HashMap::from([("max_in_flight".to_string(), Value::from(1))]);
+        let adapter = HotshotAdapter::try_from(args).unwrap();
+        let permit = adapter.in_flight_permit("node").await;
+        assert!(permit.is_some());
+        let wait = Duration::from_millis(50);
+        assert!(tokio::time::timeout(wait, adapter.in_flight_permit("node"))
+            .await
+            .is_err());
+        // the cap is per node
+        assert!(adapter.in_flight_permit("other").await.is_some());
+        drop(permit);
+        assert!(tokio::time::timeout(wait, adapter.in_flight_permit("node"))
+            .await
+            .is_ok());
+
+        let args = HashMap::from([("max_in_flight".to_string(), Value::from(0))]);
+        assert!(HotshotAdapter::try_from(args).is_err());
+    }
+
+    #[test]
+    fn test_req_id_salt() {
+        let args = HashMap::from([("req_id_salt".to_string(), Value::from(10))]);
+        let adapter = HotshotAdapter::try_from(args).unwrap();
+        assert_eq!(adapter.salted(1), 11);
+        assert_eq!(adapter.sent_req_id(1), 11);
+        assert_eq!(adapter.salted(u64::MAX), 9);
+        // without a salt, each run gets its own random one
+        let first = HotshotAdapter::try_from(HashMap::new()).unwrap();
+        let second = HotshotAdapter::try_from(HashMap::new()).unwrap();
+        assert_ne!(first.salted(1), second.salted(1));
+    }
+
+    #[tokio::test]
+    async fn test_send_txs_cancelled() {
+        let adapter = HotshotAdapter::new(
+            HotshotArgs::try_from(HashMap::new()).unwrap(),
+            Some(jrpc::DryRun::Noop),
+        )
+        .unwrap();
+        let template = RoundTemplate {
+            txs: 100,
+            tx_size: 10,
+            latency: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        let cancel = CancellationToken::new();
+        let cancel_cloned = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel_cloned.cancel();
+        });
+        let start = Instant::now();
+        let results = adapter
+            .send_txs("http://localhost:5000", 0, 0, &template, None, &cancel)
+            .await
+            .unwrap();
+        // the txs left are neither sent nor failed
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(results.sent > 0 && results.sent < 100);
+        assert_eq!(results.failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_txs_at_rate() {
+        let adapter = HotshotAdapter::new(
+            HotshotArgs::try_from(HashMap::new()).unwrap(),
+            Some(jrpc::DryRun::Noop),
+        )
+        .unwrap();
+        let template = RoundTemplate {
+            txs: 30,
+            tx_size: 10,
+            rate: Some(100),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let results = adapter
+            .send_txs(
+                "http://localhost:5000",
+                0,
+                0,
+                &template,
+                None,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        // 3 bursts of 10 txs, 100ms apart
+        assert!(start.elapsed() >= 2 * burst::BURST_DURATION);
+        assert_eq!(results.sent, 30);
+        assert_eq!(results.bytes_sent, 300);
+        assert_eq!(results.latencies.len(), 3);
+    }
+
+    #[test]
+    fn test_rpc_method() {
+        let args = HotshotArgs::try_from(HashMap::new()).unwrap();
+        assert_eq!(args.rpc_method, RPC_METHOD);
+        let args = HashMap::from([("rpc_method".to_string(), Value::from("submit_transaction"))]);
+        let args = HotshotArgs::try_from(args).unwrap();
+        assert_eq!(args.rpc_method, "submit_transaction");
+    }
 
     #[test]
     fn test_parse_endpoints() {
@@ -173,4 +1148,32 @@ mod tests {
         assert_eq!(known_ips[1], "192.168.104.4");
         assert_eq!(known_ips[2], "192.168.104.5");
     }
+
+    #[test]
+    fn test_parse_endpoints_malformed() {
+        let resp = r#"/ip4/192.168.104.3/udp/3000/quic-v1/p2p/12D3KooWPnJybf5PYvQBYeVrFPRR4BfzPzHohdtBp5R4372CPcNp
+not a multiaddr
+/ip4/999.168.104.4/udp/3000
+
+/ip4/192.168.104.5/udp/3000/quic-v1/p2p/12D3KooWMhCH2B3bWm9TVzvtntPVMyctNgiNb2GKKWFjxBxqD1md
+"#;
+        let known_ips = parse_endpoints(resp).unwrap();
+        assert_eq!(known_ips, vec!["192.168.104.3", "192.168.104.5"]);
+
+        assert!(parse_endpoints("garbage\n/ip4/nope\n").is_err());
+        assert!(parse_endpoints("").is_err());
+    }
+
+    #[test]
+    fn test_parse_endpoints_ip6_dns() {
+        let resp = r#"/ip6/fd00::104:3/udp/3000/quic-v1
+/dns4/node-1.example.com/udp/3000/quic-v1
+/dns/node-2.example.com/tcp/3000
+/udp/3000"#;
+        let known_ips = parse_endpoints(resp).unwrap();
+        assert_eq!(
+            known_ips,
+            vec!["[fd00::104:3]", "node-1.example.com", "node-2.example.com"]
+        );
+    }
 }
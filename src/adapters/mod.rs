@@ -2,8 +2,9 @@ use serde_yaml::Value;
 /// Adapter trait for implementing different RPC adapters.
 /// Each adapter should implement the methods to load endpoints and send transactions.
 use std::{collections::HashMap, sync::Arc};
+use tokio_util::sync::CancellationToken;
 
-use crate::{common, config};
+use crate::{common, config, jrpc};
 
 pub trait Adapter {
     /// Load the RPC endpoints (peers) based on the provided arguments.
@@ -24,26 +25,160 @@ pub trait Adapter {
     ) -> impl std::future::Future<Output = Result<bool, common::TestrpcError>> + Send;
 
     /// Send transactions to the given RPC URL.
-    /// This function should be implemented by each adapter to send transactions to the RPC URL.
+    /// This function should be implemented by each adapter to send transactions to the RPC URL,
+    /// based on the given round template (number of txs, size, latency between txs).
+    /// Once `cancel` is cancelled, no more txs should be sent: the txs left are neither
+    /// counted as sent nor failed.
     /// Returns a future that resolves to RoundResults.
     fn send_txs(
         &self,
         rpc_url: &str,
         req_id: u64,
         iteration: u32,
-        num_txs: usize,
-        tx_size: usize,
+        template: &config::RoundTemplate,
         timeout: Option<std::time::Duration>,
+        cancel: &CancellationToken,
     ) -> impl std::future::Future<Output = Result<common::RoundResults, common::TestrpcError>> + Send;
+
+    /// Id of the request as sent to the node, for the logs and events to match the requests the
+    /// node receives (e.g. salted by the run). The request id itself by default.
+    fn sent_req_id(&self, req_id: u64) -> u64 {
+        req_id
+    }
+
+    /// Check that the adapter can send the transactions of the template (e.g. a minimum tx size),
+    /// before any traffic is sent. Accepts any template by default.
+    fn validate_template(
+        &self,
+        _template: &config::RoundTemplate,
+    ) -> Result<(), common::TestrpcError> {
+        Ok(())
+    }
+
+    /// Fetch the status of the node at the given RPC URL (e.g. its height), to correlate the load
+    /// with the progress of the chain.
+    /// Optional, adapters which can't tell return `TestrpcError::Unsupported`.
+    fn node_status(
+        &self,
+        _rpc_url: &str,
+        _timeout: Option<std::time::Duration>,
+    ) -> impl std::future::Future<Output = Result<common::NodeStatus, common::TestrpcError>> + Send
+    {
+        async { Err(common::TestrpcError::Unsupported("node_status".to_string())) }
+    }
 }
 
+pub mod eth;
+pub mod grpc;
 pub mod hotshot;
 
+/// Adapter selected by the config, dispatching to the actual implementation
+pub enum AnyAdapter {
+    Hotshot(Box<hotshot::HotshotAdapter>),
+    Grpc(Box<grpc::GrpcAdapter>),
+    Eth(Box<eth::EthAdapter>),
+}
+
+impl Adapter for AnyAdapter {
+    async fn load_endpoints(
+        &self,
+        args: HashMap<String, Value>,
+    ) -> Result<Vec<String>, common::TestrpcError> {
+        match self {
+            AnyAdapter::Hotshot(adapter) => adapter.load_endpoints(args).await,
+            AnyAdapter::Grpc(adapter) => adapter.load_endpoints(args).await,
+            AnyAdapter::Eth(adapter) => adapter.load_endpoints(args).await,
+        }
+    }
+
+    async fn ping_endpoint(
+        &self,
+        rpc_url: &str,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<bool, common::TestrpcError> {
+        match self {
+            AnyAdapter::Hotshot(adapter) => adapter.ping_endpoint(rpc_url, timeout).await,
+            AnyAdapter::Grpc(adapter) => adapter.ping_endpoint(rpc_url, timeout).await,
+            AnyAdapter::Eth(adapter) => adapter.ping_endpoint(rpc_url, timeout).await,
+        }
+    }
+
+    async fn send_txs(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        iteration: u32,
+        template: &config::RoundTemplate,
+        timeout: Option<std::time::Duration>,
+        cancel: &CancellationToken,
+    ) -> Result<common::RoundResults, common::TestrpcError> {
+        match self {
+            AnyAdapter::Hotshot(adapter) => {
+                adapter
+                    .send_txs(rpc_url, req_id, iteration, template, timeout, cancel)
+                    .await
+            }
+            AnyAdapter::Grpc(adapter) => {
+                adapter
+                    .send_txs(rpc_url, req_id, iteration, template, timeout, cancel)
+                    .await
+            }
+            AnyAdapter::Eth(adapter) => {
+                adapter
+                    .send_txs(rpc_url, req_id, iteration, template, timeout, cancel)
+                    .await
+            }
+        }
+    }
+
+    fn sent_req_id(&self, req_id: u64) -> u64 {
+        match self {
+            AnyAdapter::Hotshot(adapter) => adapter.sent_req_id(req_id),
+            AnyAdapter::Grpc(adapter) => adapter.sent_req_id(req_id),
+            AnyAdapter::Eth(adapter) => adapter.sent_req_id(req_id),
+        }
+    }
+
+    fn validate_template(
+        &self,
+        template: &config::RoundTemplate,
+    ) -> Result<(), common::TestrpcError> {
+        match self {
+            AnyAdapter::Hotshot(adapter) => adapter.validate_template(template),
+            AnyAdapter::Grpc(adapter) => adapter.validate_template(template),
+            AnyAdapter::Eth(adapter) => adapter.validate_template(template),
+        }
+    }
+
+    async fn node_status(
+        &self,
+        rpc_url: &str,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<common::NodeStatus, common::TestrpcError> {
+        match self {
+            AnyAdapter::Hotshot(adapter) => adapter.node_status(rpc_url, timeout).await,
+            AnyAdapter::Grpc(adapter) => adapter.node_status(rpc_url, timeout).await,
+            AnyAdapter::Eth(adapter) => adapter.node_status(rpc_url, timeout).await,
+        }
+    }
+}
+
+/// Create the adapter for the given config, the arguments are parsed by the adapter.
 pub fn new_adapter(
     adapter_cfg: config::AdapterConfig,
+    args: HashMap<String, Value>,
+    dry_run: Option<jrpc::DryRun>,
 ) -> Result<Arc<impl Adapter>, common::TestrpcError> {
     match adapter_cfg {
-        config::AdapterConfig::Hotshot => Ok(Arc::new(hotshot::HotshotAdapter::new())),
+        config::AdapterConfig::Hotshot => Ok(Arc::new(AnyAdapter::Hotshot(Box::new(
+            hotshot::HotshotAdapter::new(hotshot::HotshotArgs::try_from(args)?, dry_run)?,
+        )))),
+        config::AdapterConfig::Grpc => Ok(Arc::new(AnyAdapter::Grpc(Box::new(
+            grpc::GrpcAdapter::new(grpc::GrpcArgs::try_from(args)?, dry_run),
+        )))),
+        config::AdapterConfig::Eth => Ok(Arc::new(AnyAdapter::Eth(Box::new(
+            eth::EthAdapter::new(eth::EthArgs::try_from(args)?, dry_run)?,
+        )))),
         _ => Err(common::TestrpcError::UnsupportedAdapter(
             adapter_cfg.to_string(),
         )),
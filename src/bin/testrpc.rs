@@ -1,59 +1,345 @@
-use clap::Parser;
-use std::{env, sync::Arc, time::Duration};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use testrpc::{common, config, ctx, logging, runner, signal};
+use testrpc::{
+    common, compare, config, ctx, health, jrpc, logging, metrics, mock, rng, runner, signal,
+};
 
+/// Format of the printed results
+#[derive(ValueEnum, Debug, Clone, Default)]
+enum OutputFormat {
+    #[default]
+    Yaml,
+    Json,
+    /// One row per round
+    Csv,
+}
+
+/// Mode of `--dry-run`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+enum DryRunMode {
+    /// Send the requests
+    #[value(alias = "false")]
+    Off,
+    /// Answer the requests after a fixed delay standing for the network
+    #[value(alias = "true")]
+    Noop,
+    /// Generate, serialize and pace the requests like a real run, only the network write is skipped
+    Realistic,
+}
+
+impl From<DryRunMode> for Option<jrpc::DryRun> {
+    fn from(mode: DryRunMode) -> Self {
+        match mode {
+            DryRunMode::Off => None,
+            DryRunMode::Noop => Some(jrpc::DryRun::Noop),
+            DryRunMode::Realistic => Some(jrpc::DryRun::Realistic),
+        }
+    }
+}
+
+/// What to do with the config
+#[derive(Subcommand, Debug, Clone, Default, PartialEq)]
+enum Command {
+    /// Run the flow (the default)
+    #[default]
+    Run,
+    /// Load the endpoints, print them with their ping status and exit
+    Endpoints,
+    /// Validate the config and endpoints, print a summary of the flow and exit without running it
+    Validate,
+    /// Compare the results of two runs (JSON or YAML, e.g. from --output-file), failing on regressions
+    Compare {
+        /// Results of the reference run
+        before: String,
+        /// Results of the run to check
+        after: String,
+        /// Change (as a ratio, e.g. 0.05 for 5%) a metric can get worse by without failing
+        #[clap(long, default_value = "0.1", value_parser = parse_ratio)]
+        tolerance: f64,
+    },
+}
+
+/// Send transactions to the rpcs of a network, following the flow of a config file
 #[derive(Parser, Debug, Clone)]
 struct Opts {
-    #[clap(short = 'f', long, default_value = "hotshot.testrpc.yaml")]
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[clap(
+        short = 'f',
+        long,
+        default_value = "hotshot.testrpc.yaml",
+        global = true
+    )]
     file: String,
-    #[clap(long, default_value = "false", env = "DRY_RUN")]
-    dry_run: bool,
-    #[clap(long, default_value = "false")]
+    /// Don't send the requests: `--dry-run` (or `--dry-run=noop`) answers them after a fixed delay,
+    /// `--dry-run=realistic` right away to estimate the client-side overhead. Overrides `dry_run` of the config
+    #[clap(
+        long,
+        value_enum,
+        env = "DRY_RUN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "noop",
+        global = true
+    )]
+    dry_run: Option<DryRunMode>,
+    /// Use dummy rpcs in dry run, or start in-process mock rpc servers otherwise (num_of_nodes, 4 by default)
+    #[clap(long, default_value = "false", global = true)]
     gen_mock_rpcs: bool,
-    #[clap(long)]
+    #[clap(long, global = true)]
     log_file: Option<String>,
-    #[clap(long, default_value = "debug")]
+    /// Rotation of the log file: never, hourly, daily or size:<MB>
+    #[clap(long, global = true)]
+    log_rotation: Option<logging::LogRotation>,
+    #[clap(long, default_value = "debug", global = true)]
     log_level: String,
-    #[clap(long, default_value = "10")]
+    /// Don't log every request and response, only their warnings and errors
+    #[clap(long, default_value = "false", global = true)]
+    quiet: bool,
+    /// Export the round spans and the results to the OTLP (gRPC) collector at the given endpoint
+    #[clap(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT", global = true)]
+    otlp_endpoint: Option<String>,
+    #[clap(long, default_value = "10", global = true)]
     init_retries: u32,
+    /// Same as the `validate` command
+    #[clap(long, default_value = "false", global = true)]
+    validate_only: bool,
+    /// Render a live progress line instead of scrolling through the logs, if stdout is a terminal
+    #[clap(long, default_value = "false", global = true)]
+    progress: bool,
+    /// Address to serve prometheus metrics on during the run (e.g. 0.0.0.0:9090)
+    #[clap(long, global = true)]
+    metrics_addr: Option<String>,
+    /// Address to serve the /healthz and /readyz probes on (e.g. 0.0.0.0:8080)
+    #[clap(long, global = true)]
+    health_addr: Option<String>,
+    /// Format of the results
+    #[clap(long, value_enum, default_value_t, global = true)]
+    output_format: OutputFormat,
+    /// Write the results to the given file instead of stdout
+    #[clap(long, global = true)]
+    output_file: Option<String>,
+    /// Number of buckets of the latency histogram printed after the results, 0 to disable it
+    #[clap(long, default_value = "10", global = true)]
+    histogram_buckets: usize,
+    /// Write an event as one JSON line to the given file when each request starts sending and is done
+    #[clap(long, global = true)]
+    perf_file: Option<String>,
+    /// Append the results of the run as one JSON line to the given file
+    #[clap(long, global = true)]
+    results_file: Option<String>,
+    /// Seed of the random payloads and ids, a random seed is logged if not set
+    #[clap(long, global = true)]
+    seed: Option<u64>,
+    /// Run the whole flow the given number of times back-to-back, reusing the loaded endpoints.
+    /// The results of each flow are printed, followed by their mean and standard deviation.
+    #[clap(long, default_value = "1", global = true)]
+    repeat_flow: u32,
+    /// Abort before sending any tx if fewer endpoints answer the initial ping
+    #[clap(long, global = true)]
+    require_reachable: Option<usize>,
+    /// Exit with a nonzero code if any tx failed, for CI
+    #[clap(long, default_value = "false", global = true)]
+    fail_on_error: bool,
+    /// Exit with a nonzero code if the ratio (0 to 1) of failed txs exceeds the given one, for CI
+    #[clap(long, value_parser = parse_ratio, global = true)]
+    max_failure_ratio: Option<f64>,
+}
+
+/// Parse a ratio between 0 and 1
+fn parse_ratio(ratio: &str) -> Result<f64, String> {
+    match ratio.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        Ok(ratio) => Err(format!("{ratio} is not between 0 and 1")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Path of the output file of the given repetition of the flow, numbered before the extension
+fn repetition_path(path: &str, repetition: u32) -> String {
+    let path = std::path::Path::new(path);
+    let numbered = match path.extension() {
+        Some(extension) => {
+            path.with_extension(format!("{repetition}.{}", extension.to_string_lossy()))
+        }
+        None => path.with_extension(repetition.to_string()),
+    };
+    numbered.display().to_string()
+}
+
+/// Line appended to the results file for each run
+#[derive(Serialize)]
+struct ResultsRecord<'a> {
+    /// RFC 3339 timestamp of the end of the run
+    timestamp: String,
+    config_file: &'a str,
+    results: &'a common::FlowResults,
+}
+
+/// Append the results as one JSON line to the given file, creating it if needed
+fn append_results(
+    path: &str,
+    config_file: &str,
+    results: &common::FlowResults,
+) -> Result<(), common::TestrpcError> {
+    let record = ResultsRecord {
+        timestamp: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+        config_file,
+        results,
+    };
+    let line = serde_json::to_string(&record)
+        .map_err(|e| common::TestrpcError::OutputError(e.to_string()))?;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{line}"))
+        .map_err(|e| common::TestrpcError::OutputError(format!("Failed to write {path}: {e}")))
+}
+
+/// Serialize the results in the given format
+fn serialize_results(
+    results: &common::FlowResults,
+    format: &OutputFormat,
+) -> Result<String, common::TestrpcError> {
+    match format {
+        OutputFormat::Yaml => serde_yaml::to_string(results)
+            .map_err(|e| common::TestrpcError::OutputError(e.to_string())),
+        OutputFormat::Json => serde_json::to_string_pretty(results)
+            .map_err(|e| common::TestrpcError::OutputError(e.to_string())),
+        OutputFormat::Csv => Ok(results.to_csv()),
+    }
+}
+
+/// Log why the flow stopped, warning if it stopped on an error
+fn log_stop_reason(reason: &ctx::StopReason) {
+    tracing::info!("Flow stopped: {}", reason);
+    if let ctx::StopReason::Error(e) = reason {
+        tracing::warn!("Flow stopped on an error: {}", e);
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), common::TestrpcError> {
     let opts: Opts = Opts::parse();
-    if let Some(log_file) = opts.log_file {
-        env::set_var("RUST_LOG_FILE", log_file.clone());
-        println!("Using log file: {}", log_file.clone());
+    let command = if opts.validate_only {
+        Command::Validate
+    } else {
+        opts.command.clone().unwrap_or_default()
+    };
+    if let Command::Compare {
+        before,
+        after,
+        tolerance,
+    } = &command
+    {
+        let deltas = compare::compare(
+            &compare::load_results(before)?,
+            &compare::load_results(after)?,
+        );
+        print!("{}", compare::format_comparison(&deltas, *tolerance));
+        let regressions = deltas
+            .iter()
+            .filter(|delta| delta.is_regression(*tolerance))
+            .map(|delta| delta.metric)
+            .collect::<Vec<_>>();
+        if !regressions.is_empty() {
+            return Err(common::TestrpcError::Regression(regressions.join(", ")));
+        }
+        println!("No regression of {after} over {before}");
+        return Ok(());
+    }
+    // The format and span events are still taken from the environment
+    let mut logging_config = logging::LoggingConfig::from_env();
+    if opts.log_file.is_some() {
+        logging_config.file = opts.log_file.clone();
+    }
+    if let Some(log_rotation) = &opts.log_rotation {
+        logging_config.rotation = log_rotation.clone();
+    }
+    if let Some(log_file) = &logging_config.file {
+        println!("Using log file: {log_file}");
     } else {
         println!("Output log to stdout");
     }
-    env::set_var("RUST_LOG", opts.log_level.clone());
     println!("Using log level: {}", &opts.log_level);
+    logging_config.level = opts.log_level.clone();
+    logging_config.quiet = opts.quiet;
+    if let Some(otlp_endpoint) = &opts.otlp_endpoint {
+        println!("Exporting to OTLP collector: {otlp_endpoint}");
+    }
+    logging_config.otlp_endpoint = opts.otlp_endpoint.clone();
 
-    let _log_guard = logging::initialize_logging();
+    let _log_guard = logging::initialize_logging_with(logging_config);
+    let _perf_guard = match &opts.perf_file {
+        Some(perf_file) => Some(logging::perf::init_perf_file(perf_file)?),
+        None => None,
+    };
     let ctx = Arc::new(ctx::Context::new());
     let start = std::time::Instant::now();
 
     tracing::info!("Starting testrpc with config file: {}", &opts.file);
+    let seed = rng::init(opts.seed);
+    tracing::info!(
+        "Using seed {}, pass --seed {} to reproduce the run",
+        seed,
+        seed
+    );
 
-    if opts.dry_run {
-        tracing::info!("Dry run, we will not send any RPCs");
-        env::set_var("DRY_RUN", "true");
+    // ready once the endpoints are loaded and one of them is reachable
+    let ready = Arc::new(AtomicBool::new(false));
+    if let Some(health_addr) = &opts.health_addr {
+        health::serve(ctx.clone(), health_addr, ready.clone()).await?;
     }
 
-    let cfg = config::load_config(opts.file.as_str()).unwrap();
+    let mut cfg = config::load_config(opts.file.as_str())?;
+    if let Some(dry_run) = opts.dry_run {
+        cfg.dry_run = dry_run.into();
+    }
+    match cfg.dry_run {
+        Some(jrpc::DryRun::Realistic) => {
+            tracing::info!("Realistic dry run, the RPCs are built but not sent")
+        }
+        Some(jrpc::DryRun::Noop) => tracing::info!("Dry run, we will not send any RPCs"),
+        None => {}
+    }
+    config::validate(&cfg)?;
+    runner::validate_templates(&cfg)?;
     let retries = opts.init_retries;
-    let cfg_rpcs = cfg.clone().rpcs.unwrap_or_default();
-    let rpc_urls = if cfg_rpcs.is_empty() {
+    let cfg_rpcs = match &cfg.rpcs_file {
+        Some(rpcs_file) => config::read_rpcs_file(rpcs_file)?,
+        None => cfg.clone().rpcs.unwrap_or_default(),
+    };
+    // Keeps the mock servers running until the end of the run
+    let mut mock_servers = Vec::new();
+    let rpc_urls = if !cfg_rpcs.is_empty() {
         cfg_rpcs
-    } else if opts.dry_run && opts.gen_mock_rpcs {
+    } else if cfg.dry_run.is_some() && opts.gen_mock_rpcs {
         let num_of_nodes = cfg.num_of_nodes.unwrap_or(4);
         let mut urls = Vec::new();
         for i in 0..num_of_nodes {
             urls.push(format!("http://dummy:{}", 5000 + i));
         }
         urls
+    } else if opts.gen_mock_rpcs {
+        for _ in 0..cfg.num_of_nodes.unwrap_or(4) {
+            mock_servers.push(mock::MockRpcServer::start().await?);
+        }
+        let urls = mock_servers
+            .iter()
+            .map(|server| server.url())
+            .collect::<Vec<_>>();
+        tracing::info!("Started mock RPC servers: {:?}", urls);
+        urls
     } else {
         let cfg_clone = cfg.clone();
         let urls = common::retry(
@@ -71,52 +357,210 @@ async fn main() -> Result<(), common::TestrpcError> {
         }
         urls
     };
+    cfg.expand_to_all(rpc_urls.len());
 
-    match runner::ping_endpoints(
+    let reachable = match runner::ping_endpoints(
         cfg.adapter.clone(),
+        cfg.args.clone(),
         rpc_urls.clone(),
         cfg.timeout
             .or(Some(15))
             .map(|t| Duration::from_secs(t as u64)),
+        cfg.dry_run,
     )
     .await
     {
-        Ok(0) => {
-            tracing::warn!("No reachable endpoints found");
-        }
-        Ok(n) => {
-            tracing::info!("{} endpoints are reachable", n);
+        Ok(results) => {
+            println!("{}", runner::format_ping_results(&results));
+            if let Some(required) = opts.require_reachable {
+                runner::check_reachable(&results, required)?;
+            }
+            let reachable = results
+                .into_iter()
+                .map(|(_, reachable, _)| reachable)
+                .collect::<Vec<_>>();
+            match reachable.iter().filter(|reachable| **reachable).count() {
+                0 => tracing::warn!("No reachable endpoints found"),
+                n => {
+                    tracing::info!("{} endpoints are reachable", n);
+                    ready.store(true, Ordering::SeqCst);
+                }
+            }
+            Some(reachable)
         }
+        Err(e) if opts.require_reachable.is_some() => return Err(e),
         Err(e) => {
             tracing::warn!("Failed to ping endpoints: {}", e);
+            None
         }
+    };
+
+    if command == Command::Endpoints {
+        if reachable.is_none() {
+            // the table wasn't printed
+            for rpc_url in rpc_urls.iter() {
+                println!("{rpc_url}");
+            }
+        }
+        println!("{} endpoints loaded from {}", rpc_urls.len(), &opts.file);
+        return Ok(());
     }
 
     if let Some(num_of_nodes) = cfg.num_of_nodes {
         let actual_num_of_nodes = rpc_urls.len();
         if actual_num_of_nodes != num_of_nodes {
-            return Err(common::TestrpcError::WrongNumberOfNodes(
+            if cfg.strict_node_count {
+                return Err(common::TestrpcError::WrongNumberOfNodes(
+                    num_of_nodes,
+                    actual_num_of_nodes,
+                ));
+            }
+            let removed_rounds = cfg.truncate_rpcs(actual_num_of_nodes);
+            tracing::warn!(
+                "Expected {} nodes, found {}, running with them ({} rounds left without endpoints are skipped)",
                 num_of_nodes,
                 actual_num_of_nodes,
-            ));
+                removed_rounds
+            );
+            if cfg.rounds.is_empty() {
+                return Err(common::TestrpcError::LoadEndpointsError(
+                    "No round has an available endpoint".to_string(),
+                ));
+            }
+        }
+    }
+
+    let rpc_urls = match reachable {
+        Some(reachable) if cfg.skip_unreachable && reachable.contains(&false) => {
+            let rpc_urls = rpc_urls
+                .into_iter()
+                .zip(reachable.iter())
+                .filter_map(|(rpc_url, reachable)| reachable.then_some(rpc_url))
+                .collect::<Vec<_>>();
+            let removed_rounds = cfg.retain_rpcs(&reachable);
+            tracing::warn!(
+                "Skipping {} unreachable endpoints and {} rounds left without endpoints",
+                reachable.len() - rpc_urls.len(),
+                removed_rounds
+            );
+            if cfg.rounds.is_empty() {
+                return Err(common::TestrpcError::LoadEndpointsError(
+                    "No round has a reachable endpoint".to_string(),
+                ));
+            }
+            rpc_urls
+        }
+        _ => rpc_urls,
+    };
+
+    if command == Command::Validate {
+        println!("Config {} is valid", &opts.file);
+        println!("rpcs: {}", rpc_urls.len());
+        println!("rounds: {}", cfg.rounds.len());
+        match cfg.total_planned_txs() {
+            Some(total_txs) => println!(
+                "iterations: {}, total txs: {}",
+                cfg.iterations.unwrap_or_default(),
+                total_txs
+            ),
+            None => println!(
+                "iterations: unlimited, txs per cycle of rounds: {}",
+                (1..=cfg.rounds.len() as u32)
+                    .map(|i| cfg.planned_txs(i))
+                    .sum::<usize>()
+            ),
         }
+        return Ok(());
+    }
+
+    if let Some(metrics_addr) = &opts.metrics_addr {
+        metrics::serve(ctx.clone(), metrics_addr).await?;
     }
 
     let ctx_cloned = ctx.clone();
+    let mut handle = tokio::spawn(async move {
+        let repeat_flow = opts.repeat_flow.max(1);
+        let mut flows = Vec::new();
+        for repetition in 1..=repeat_flow {
+            if repeat_flow > 1 {
+                tracing::info!("Running flow {}/{}", repetition, repeat_flow);
+            }
+            // each flow gets its own context, stopped along with the one of the run
+            let flow_ctx = ctx_cloned.child();
+            let flow_start = match repetition {
+                1 => start,
+                _ => std::time::Instant::now(),
+            };
+            let options = runner::RunOptions {
+                progress: opts.progress,
+            };
+            let round_results =
+                match runner::run_with(flow_ctx.clone(), cfg.clone(), rpc_urls.clone(), options)
+                    .await
+                {
+                    Ok(round_results) => round_results,
+                    Err(e) => {
+                        let reason = ctx::StopReason::Error(e.to_string());
+                        log_stop_reason(&reason);
+                        ctx_cloned.stop(reason);
+                        return Err(e);
+                    }
+                };
+            let time_elapsed = flow_start.elapsed();
+            let mut results =
+                common::FlowResults::new_from_round_results(round_results, time_elapsed);
+            results.stop_reason = flow_ctx.stop_reason();
+            if let Some(reason) = &results.stop_reason {
+                log_stop_reason(reason);
+            }
+            if let Some(ramp) = &cfg.ramp {
+                results.set_ramp_breaking_point(ramp);
+            }
+            logging::record_results(&results);
+            if let Some(results_file) = &opts.results_file {
+                append_results(results_file, &opts.file, &results)?;
+                tracing::info!("Results appended to {}", results_file);
+            }
+            let output = serialize_results(&results, &opts.output_format)?;
+            if let Some(output_file) = &opts.output_file {
+                let output_file = match repeat_flow {
+                    1 => output_file.clone(),
+                    _ => repetition_path(output_file, repetition),
+                };
+                std::fs::write(&output_file, output).map_err(|e| {
+                    common::TestrpcError::OutputError(format!("Failed to write {output_file}: {e}"))
+                })?;
+                tracing::info!("Results written to {}", output_file);
+            } else {
+                println!("---RESULTS--\n");
+                println!("{output}");
+                println!("---END RESULTS--\n");
+            }
+            if opts.histogram_buckets > 0 {
+                if let Some(histogram) = results.latency_histogram(opts.histogram_buckets) {
+                    println!("Latency histogram:\n{histogram}");
+                }
+            }
+            flows.push(results);
+            if ctx_cloned.stop_reason().is_some() {
+                tracing::info!("Run stopped, skipping the remaining flows");
+                break;
+            }
+        }
+        if repeat_flow > 1 {
+            println!("{}", common::format_flows_summary(&flows));
+        }
+        for results in flows.iter() {
+            results.check_failures(opts.fail_on_error, opts.max_failure_ratio)?;
+        }
+        Ok::<(), common::TestrpcError>(())
+    });
     tokio::select! {
-        _ = tokio::spawn(async move {
-            let round_results = runner::run(ctx_cloned, cfg.clone(), rpc_urls)
-                .await
-                .unwrap();
-            let time_elapsed = start.elapsed();
-            let results = common::FlowResults::new_from_round_results(round_results, time_elapsed);
-            let results_yaml = serde_yaml::to_string(&results).unwrap();
-            println!("---RESULTS--\n");
-            println!("{results_yaml}");
-            println!("---END RESULTS--\n");
-        }) => {}
+        res = &mut handle => res??,
         _ = signal::wait_exit_signals() => {
-            ctx.stop();
+            // Let the runner drain the in-flight round and print the results
+            ctx.stop(ctx::StopReason::Signal);
+            handle.await??;
         }
     }
     Ok(())
@@ -1,5 +1,13 @@
+pub mod broadcast;
+pub mod burst;
+pub mod payload;
+pub mod probe;
+pub mod replay;
+
 use serde::{Deserialize, Serialize};
-use std::{future::Future, pin::Pin, time::Duration};
+
+use crate::{config::RampConfig, ctx::StopReason};
+use std::{collections::HashMap, future::Future, pin::Pin, time::Duration};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,21 +26,230 @@ pub enum TestrpcError {
     LoadRoundTemplateError(String),
     #[error("RPC error: {0}")]
     RpcError(String),
+    #[error("Request error: {0}")]
+    RequestError(String),
+    /// Failure of a request to an endpoint, with the context of the request
+    #[error("Request {req_id} ({method}) to {endpoint} failed: {cause}")]
+    RequestFailed {
+        endpoint: String,
+        req_id: u64,
+        method: String,
+        #[source]
+        cause: Box<TestrpcError>,
+    },
     #[error("Execution error: {0}")]
     ExecutionError(String),
     #[error("Termination error: {0}")]
     TerminationError(String),
+    #[error("Server error: {0}")]
+    ServerError(String),
+    #[error("Output error: {0}")]
+    OutputError(String),
+    #[error("Too many failed txs: {0}")]
+    FailedTxs(String),
+    #[error("Regression: {0}")]
+    Regression(String),
+    #[error("Unsupported by the adapter: {0}")]
+    Unsupported(String),
     #[error("Join error: {0}")]
     JoinError(#[from] tokio::task::JoinError),
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl TestrpcError {
+    /// Attach the context of the request that failed with this error, unless it already has one
+    pub fn for_request(self, endpoint: &str, req_id: u64, method: &str) -> Self {
+        match self {
+            TestrpcError::RequestFailed { .. } => self,
+            cause => TestrpcError::RequestFailed {
+                endpoint: endpoint.to_string(),
+                req_id,
+                method: method.to_string(),
+                cause: Box::new(cause),
+            },
+        }
+    }
+
+    /// The error without the context of its request
+    pub fn cause(&self) -> &TestrpcError {
+        match self {
+            TestrpcError::RequestFailed { cause, .. } => cause,
+            e => e,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct RoundResults {
+    /// Iteration of the round, None for aggregated results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<u32>,
     pub sent: usize,
     pub failed: usize,
-    // TODO: bytes
-    // pub bytes_sent: usize,
-    // pub bytes_failed: usize,
+    pub bytes_sent: usize,
+    pub bytes_failed: usize,
+    /// Latency statistics of the RPC calls, computed from `latencies`
+    pub latency: Option<LatencyStats>,
+    /// Raw latency samples of the RPC calls
+    #[serde(skip)]
+    pub latencies: Vec<Duration>,
+    /// Results of each node, keyed by rpc url
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub per_node: HashMap<String, RoundResults>,
+    /// Status of each node at the end of the round, keyed by rpc url.
+    /// Collected if `collect_status` is set, not merged into aggregated results.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub node_status: HashMap<String, NodeStatus>,
+    /// Duration of the round, from its first send to its last completion.
+    /// None for aggregated results, see `set_duration`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<Duration>,
+    /// Achieved send rate of the round in txs per second, computed from `duration`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_rate: Option<f64>,
+    /// Fraction of the configured txs sent in the round, set with the adaptive load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load: Option<f64>,
+    /// Time from the start of the flow to the completion of the round, None for aggregated results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed: Option<Duration>,
+}
+
+/// Status of a node, as far as the adapter can tell
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct NodeStatus {
+    /// Height of the chain (e.g. block height)
+    #[serde(
+        default,
+        alias = "block_height",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub height: Option<u64>,
+    /// Number of transactions waiting in the mempool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mempool_size: Option<u64>,
+}
+
+impl RoundResults {
+    /// Accumulate the given results into self.
+    /// Latency samples are merged, call `compute_latency_stats` to refresh the stats.
+    pub fn merge(&mut self, other: &RoundResults) {
+        self.sent += other.sent;
+        self.failed += other.failed;
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_failed += other.bytes_failed;
+        self.latencies.extend_from_slice(&other.latencies);
+        for (rpc_url, node_results) in other.per_node.iter() {
+            self.per_node
+                .entry(rpc_url.clone())
+                .or_default()
+                .merge(node_results);
+        }
+    }
+
+    /// Accumulate the results of the given node into self and its per node results
+    pub fn merge_node(&mut self, rpc_url: &str, node_results: &RoundResults) {
+        self.merge(node_results);
+        self.per_node
+            .entry(rpc_url.to_string())
+            .or_default()
+            .merge(node_results);
+    }
+
+    /// Ratio of the txs that failed, 0 if no txs were sent
+    pub fn failure_ratio(&self) -> f64 {
+        let total = self.sent + self.failed;
+        if total == 0 {
+            return 0.0;
+        }
+        self.failed as f64 / total as f64
+    }
+
+    /// Set the duration of the round and its achieved send rate
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = Some(duration);
+        self.tx_rate = (!duration.is_zero()).then(|| self.sent as f64 / duration.as_secs_f64());
+    }
+
+    /// Compute the latency stats from the collected samples, including per node
+    pub fn compute_latency_stats(&mut self) {
+        self.latency = LatencyStats::from_samples(&self.latencies);
+        for node_results in self.per_node.values_mut() {
+            node_results.compute_latency_stats();
+        }
+    }
+}
+
+/// Latency statistics over a set of RPC calls
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl LatencyStats {
+    /// Compute the stats of the given samples, returns None if there are no samples
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let total: Duration = sorted.iter().sum();
+        Some(Self {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: total / sorted.len() as u32,
+            p50: percentile(&sorted, 50.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+        })
+    }
+}
+
+/// Width in characters of the longest bar of a latency histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// ASCII histogram of the samples, in `buckets` buckets of equal width between the min and
+/// the max sample. Returns None if there are no samples.
+pub fn latency_histogram(samples: &[Duration], buckets: usize) -> Option<String> {
+    let min = *samples.iter().min()?;
+    let max = *samples.iter().max()?;
+    let buckets = buckets.max(1);
+    let width = (max - min).as_secs_f64() / buckets as f64;
+    let mut counts = vec![0usize; buckets];
+    for sample in samples {
+        let bucket = if width > 0.0 {
+            ((*sample - min).as_secs_f64() / width) as usize
+        } else {
+            0
+        };
+        counts[bucket.min(buckets - 1)] += 1;
+    }
+    let max_count = *counts.iter().max()?;
+    let mut histogram = String::new();
+    for (i, count) in counts.iter().enumerate() {
+        let from = min.as_secs_f64() + width * i as f64;
+        let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(max_count));
+        histogram.push_str(&format!(
+            "{:>10.3}ms - {:>10.3}ms | {:<width$} {}\n",
+            from * 1000.0,
+            (from + width) * 1000.0,
+            bar,
+            count,
+            width = HISTOGRAM_WIDTH
+        ));
+    }
+    Some(histogram)
+}
+
+/// Nearest-rank percentile of the given sorted samples
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,38 +258,216 @@ pub struct FlowResults {
     pub total: RoundResults,
     pub total_time: Duration,
     pub total_iterations: u32,
+    /// Number of txs (per rpc) of the first ramp iteration that produced failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ramp_breaking_point: Option<usize>,
+    /// Reason the flow was stopped with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+    /// Load of each round with the adaptive load, see `RoundResults::load`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub load_trajectory: Vec<f64>,
+    /// Standard deviation of the txs sent by the rounds
+    #[serde(default)]
+    pub sent_stddev: f64,
+    /// Standard deviation of the txs failed by the rounds
+    #[serde(default)]
+    pub failed_stddev: f64,
+    /// Standard deviation of the mean latency of the rounds, None if no round has latencies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_stddev: Option<Duration>,
+    /// Iteration of the first round with failed txs, None if no tx failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_failure_iteration: Option<u32>,
+    /// Time from the start of the flow to the completion of the first round with failed txs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_failure_elapsed: Option<Duration>,
+}
+
+/// Mean and (population) standard deviation of the values, None if there are none
+pub fn mean_stddev(values: &[f64]) -> Option<(f64, f64)> {
+    if values.is_empty() {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    Some((mean, variance.sqrt()))
+}
+
+/// Table of the mean and standard deviation across the flows of their totals
+pub fn format_flows_summary(flows: &[FlowResults]) -> String {
+    let values = |metric: fn(&FlowResults) -> f64| flows.iter().map(metric).collect::<Vec<_>>();
+    let rows = [
+        ("sent", values(|flow| flow.total.sent as f64)),
+        ("failed", values(|flow| flow.total.failed as f64)),
+        (
+            "tx/s",
+            values(|flow| match flow.total_time.as_secs_f64() {
+                0.0 => 0.0,
+                secs => flow.total.sent as f64 / secs,
+            }),
+        ),
+        (
+            "p50 (ms)",
+            values(|flow| {
+                flow.total
+                    .latency
+                    .as_ref()
+                    .map_or(0.0, |latency| latency.p50.as_secs_f64() * 1000.0)
+            }),
+        ),
+    ];
+    let mut table = format!(
+        "Summary of {} flows:\n{:<8}  {:>12}  {:>12}\n",
+        flows.len(),
+        "METRIC",
+        "MEAN",
+        "STDDEV"
+    );
+    for (name, values) in rows {
+        let (mean, stddev) = mean_stddev(&values).unwrap_or_default();
+        table.push_str(&format!("{name:<8}  {mean:>12.2}  {stddev:>12.2}\n"));
+    }
+    table
 }
 
 impl FlowResults {
-    pub fn new_from_round_results(rounds: Vec<RoundResults>, total_time: Duration) -> Self {
+    pub fn new_from_round_results(mut rounds: Vec<RoundResults>, total_time: Duration) -> Self {
+        // the pipelined rounds complete out of order, the results are by iteration
+        rounds.sort_by_key(|round| round.iteration);
         let total_iterations = rounds.len() as u32;
-        let mut total = RoundResults { sent: 0, failed: 0 };
+        let mut total = RoundResults::default();
         for round in rounds.iter() {
-            total.sent += round.sent;
-            total.failed += round.failed;
+            total.merge(round);
         }
+        total.compute_latency_stats();
+        let load_trajectory = rounds.iter().filter_map(|round| round.load).collect();
+        let stddev = |values: Vec<f64>| mean_stddev(&values).map(|(_, stddev)| stddev);
+        let sent_stddev = stddev(rounds.iter().map(|round| round.sent as f64).collect());
+        let failed_stddev = stddev(rounds.iter().map(|round| round.failed as f64).collect());
+        let latency_stddev = stddev(
+            rounds
+                .iter()
+                .filter_map(|round| round.latency.as_ref())
+                .map(|latency| latency.mean.as_secs_f64())
+                .collect(),
+        )
+        .map(Duration::from_secs_f64);
+        let first_failure = rounds.iter().find(|round| round.failed > 0);
+        let first_failure_iteration = first_failure.and_then(|round| round.iteration);
+        let first_failure_elapsed = first_failure.and_then(|round| round.elapsed);
         Self {
             rounds,
             total,
             total_time,
             total_iterations,
+            ramp_breaking_point: None,
+            stop_reason: None,
+            load_trajectory,
+            sent_stddev: sent_stddev.unwrap_or_default(),
+            failed_stddev: failed_stddev.unwrap_or_default(),
+            latency_stddev,
+            first_failure_iteration,
+            first_failure_elapsed,
         }
     }
+
+    /// Check the failed txs of the flow: it fails with `fail_on_error` if any tx failed,
+    /// or if the ratio of failed txs exceeds `max_failure_ratio`
+    pub fn check_failures(
+        &self,
+        fail_on_error: bool,
+        max_failure_ratio: Option<f64>,
+    ) -> Result<(), TestrpcError> {
+        let failed = self.total.failed;
+        if fail_on_error && failed > 0 {
+            return Err(TestrpcError::FailedTxs(format!(
+                "{failed} of {} txs failed",
+                failed + self.total.sent
+            )));
+        }
+        if let Some(max_failure_ratio) = max_failure_ratio {
+            let ratio = self.total.failure_ratio();
+            if ratio > max_failure_ratio {
+                return Err(TestrpcError::FailedTxs(format!(
+                    "{:.2}% of the txs failed, above the max of {:.2}%",
+                    ratio * 100.0,
+                    max_failure_ratio * 100.0
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Find the number of txs of the first ramp iteration that produced failures
+    pub fn set_ramp_breaking_point(&mut self, ramp: &RampConfig) {
+        self.ramp_breaking_point = self
+            .rounds
+            .iter()
+            .filter(|round| round.failed > 0)
+            .filter_map(|round| round.iteration)
+            .min()
+            .map(|iteration| ramp.txs(iteration));
+    }
+
+    /// Histogram of the latencies of all the rounds, see `latency_histogram`
+    pub fn latency_histogram(&self, buckets: usize) -> Option<String> {
+        latency_histogram(&self.total.latencies, buckets)
+    }
+
+    /// CSV of the results with one row per round, the p95 latency is in milliseconds
+    /// and the achieved rate in txs per second
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("iteration,sent,failed,bytes_sent,latency_p95,tx_rate\n");
+        for round in self.rounds.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                round.iteration.map(|i| i.to_string()).unwrap_or_default(),
+                round.sent,
+                round.failed,
+                round.bytes_sent,
+                round
+                    .latency
+                    .as_ref()
+                    .map(|latency| format!("{:.3}", latency.p95.as_secs_f64() * 1000.0))
+                    .unwrap_or_default(),
+                round
+                    .tx_rate
+                    .map(|rate| format!("{rate:.1}"))
+                    .unwrap_or_default(),
+            ));
+        }
+        csv
+    }
 }
 
 /// Retry a given operation a specified number of times with a delay between attempts
 /// and optional exponential backoff.
-/// Returns Ok(()) if successful, or an error if all retries fail.
-pub async fn retry<T: Send + 'static>(
+/// Returns the result if successful, or the last error if all retries fail.
+pub async fn retry<'a, T: Send + 'static>(
+    retries: usize,
+    delay: Duration,
+    operation: impl FnMut() -> Pin<Box<dyn Future<Output = Result<T, TestrpcError>> + Send + 'a>>,
+    exponential_backoff: bool,
+) -> Result<T, TestrpcError> {
+    retry_if(retries, delay, operation, exponential_backoff, |_| true).await
+}
+
+/// Same as `retry`, but only errors for which `should_retry` returns true are retried,
+/// other errors are returned right away.
+pub async fn retry_if<'a, T: Send + 'static>(
     retries: usize,
     delay: Duration,
-    mut operation: impl FnMut() -> Pin<Box<dyn Future<Output = Result<T, TestrpcError>> + Send>>,
+    mut operation: impl FnMut() -> Pin<Box<dyn Future<Output = Result<T, TestrpcError>> + Send + 'a>>,
     exponential_backoff: bool,
+    should_retry: impl Fn(&TestrpcError) -> bool,
 ) -> Result<T, TestrpcError> {
     let mut delay = delay;
-    for _ in 0..retries {
+    for attempt in 1..=retries {
         match operation().await {
             Ok(result) => return Ok(result),
+            Err(e) if attempt == retries || !should_retry(&e) => return Err(e),
             Err(e) => {
                 tracing::warn!("Operation failed: {}. Retrying in {:?}...", e, delay);
                 tokio::time::sleep(delay).await;
@@ -86,3 +481,241 @@ pub async fn retry<T: Send + 'static>(
         "Max retries reached".to_string(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_request() {
+        let e = TestrpcError::RequestError("connection reset".to_string()).for_request(
+            "http://localhost:5000",
+            7,
+            "send_txs",
+        );
+        assert_eq!(
+            e.to_string(),
+            "Request 7 (send_txs) to http://localhost:5000 failed: Request error: connection reset"
+        );
+        assert!(matches!(e.cause(), TestrpcError::RequestError(_)));
+        // the context of the first request is kept
+        let e = e.for_request("http://localhost:5001", 8, "send_txs");
+        assert!(e.to_string().contains("http://localhost:5000"));
+    }
+
+    #[test]
+    fn test_mean_stddev() {
+        assert_eq!(mean_stddev(&[]), None);
+        assert_eq!(
+            mean_stddev(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]),
+            Some((5.0, 2.0))
+        );
+    }
+
+    #[test]
+    fn test_flow_stddev() {
+        let rounds = [(10, 0, 10), (20, 4, 30)]
+            .map(|(sent, failed, latency)| {
+                let mut round = RoundResults {
+                    sent,
+                    failed,
+                    latencies: vec![Duration::from_millis(latency)],
+                    ..Default::default()
+                };
+                round.compute_latency_stats();
+                round
+            })
+            .to_vec();
+        let results = FlowResults::new_from_round_results(rounds, Duration::from_secs(1));
+        assert_eq!(results.total.sent, 30);
+        assert_eq!(results.sent_stddev, 5.0);
+        assert_eq!(results.failed_stddev, 2.0);
+        assert_eq!(results.latency_stddev, Some(Duration::from_millis(10)));
+
+        let results = FlowResults::new_from_round_results(Vec::new(), Duration::from_secs(1));
+        assert_eq!(results.sent_stddev, 0.0);
+        assert_eq!(results.latency_stddev, None);
+    }
+
+    #[test]
+    fn test_first_failure() {
+        let rounds = [(1, 0), (2, 3), (3, 1)]
+            .map(|(iteration, failed)| RoundResults {
+                iteration: Some(iteration),
+                failed,
+                elapsed: Some(Duration::from_secs(iteration as u64)),
+                ..Default::default()
+            })
+            .to_vec();
+        let results = FlowResults::new_from_round_results(rounds.clone(), Duration::from_secs(3));
+        assert_eq!(results.first_failure_iteration, Some(2));
+        assert_eq!(results.first_failure_elapsed, Some(Duration::from_secs(2)));
+
+        let results =
+            FlowResults::new_from_round_results(rounds[..1].to_vec(), Duration::from_secs(1));
+        assert_eq!(results.first_failure_iteration, None);
+        assert_eq!(results.first_failure_elapsed, None);
+    }
+
+    #[test]
+    fn test_check_failures() {
+        let results = FlowResults::new_from_round_results(
+            vec![RoundResults {
+                sent: 9,
+                failed: 1,
+                ..Default::default()
+            }],
+            Duration::from_secs(1),
+        );
+        assert!(results.check_failures(false, None).is_ok());
+        assert!(results.check_failures(true, None).is_err());
+        assert!(results.check_failures(false, Some(0.1)).is_ok());
+        assert!(matches!(
+            results.check_failures(false, Some(0.05)),
+            Err(TestrpcError::FailedTxs(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_flows_summary() {
+        let flows = [10, 20]
+            .map(|sent| {
+                FlowResults::new_from_round_results(
+                    vec![RoundResults {
+                        sent,
+                        ..Default::default()
+                    }],
+                    Duration::from_secs(1),
+                )
+            })
+            .to_vec();
+        let summary = format_flows_summary(&flows);
+        assert!(summary.starts_with("Summary of 2 flows:"), "{summary}");
+        assert!(
+            summary.contains("sent             15.00          5.00"),
+            "{summary}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_if() {
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+        let operation = || {
+            let attempt = attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async move {
+                match attempt {
+                    0 => Err(TestrpcError::RequestError("reset".to_string())),
+                    _ => Err(TestrpcError::RpcError("rejected".to_string())),
+                }
+            }) as Pin<Box<dyn Future<Output = Result<(), TestrpcError>> + Send>>
+        };
+        let res = retry_if(5, Duration::from_millis(1), operation, true, |e| {
+            matches!(e, TestrpcError::RequestError(_))
+        })
+        .await;
+        // the request error is retried, the rpc error is returned right away
+        assert!(matches!(res, Err(TestrpcError::RpcError(_))));
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_latency_stats() {
+        assert!(LatencyStats::from_samples(&[]).is_none());
+        let samples = (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+        let stats = LatencyStats::from_samples(&samples).unwrap();
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(100));
+        assert_eq!(stats.mean, Duration::from_micros(50_500));
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p95, Duration::from_millis(95));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+    }
+
+    #[test]
+    fn test_flow_results_latency() {
+        let rounds = vec![
+            RoundResults {
+                sent: 1,
+                latencies: vec![Duration::from_millis(10)],
+                ..Default::default()
+            },
+            RoundResults {
+                sent: 1,
+                latencies: vec![Duration::from_millis(30)],
+                ..Default::default()
+            },
+        ];
+        let results = FlowResults::new_from_round_results(rounds, Duration::from_secs(1));
+        let latency = results.total.latency.unwrap();
+        assert_eq!(results.total.sent, 2);
+        assert_eq!(latency.min, Duration::from_millis(10));
+        assert_eq!(latency.max, Duration::from_millis(30));
+        assert_eq!(latency.mean, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        assert_eq!(latency_histogram(&[], 10), None);
+
+        let samples = (1..=10).map(Duration::from_millis).collect::<Vec<_>>();
+        let histogram = latency_histogram(&samples, 3).unwrap();
+        let counts = histogram
+            .lines()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![3, 3, 4]);
+        assert!(histogram.starts_with("     1.000ms -      4.000ms | "));
+
+        // all the samples fall in the first bucket when they are equal
+        let histogram = latency_histogram(&[Duration::from_millis(5); 4], 3).unwrap();
+        assert_eq!(histogram.lines().count(), 3);
+        assert!(histogram.lines().next().unwrap().ends_with(" 4"));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let mut round = RoundResults {
+            iteration: Some(1),
+            sent: 2,
+            failed: 1,
+            bytes_sent: 200,
+            latencies: vec![Duration::from_millis(10)],
+            ..Default::default()
+        };
+        round.compute_latency_stats();
+        round.set_duration(Duration::from_millis(500));
+        let rounds = vec![
+            round,
+            RoundResults {
+                iteration: Some(2),
+                ..Default::default()
+            },
+        ];
+        let results = FlowResults::new_from_round_results(rounds, Duration::from_secs(1));
+        assert_eq!(
+            results.to_csv(),
+            "iteration,sent,failed,bytes_sent,latency_p95,tx_rate\n1,2,1,200,10.000,4.0\n2,0,0,0,,\n"
+        );
+    }
+
+    #[test]
+    fn test_per_node_results() {
+        let node_results = RoundResults {
+            sent: 2,
+            failed: 1,
+            ..Default::default()
+        };
+        let mut round = RoundResults::default();
+        round.merge_node("http://a", &node_results);
+        round.merge_node("http://b", &node_results);
+        assert_eq!(round.sent, 4);
+        assert_eq!(round.per_node["http://a"].sent, 2);
+
+        let results =
+            FlowResults::new_from_round_results(vec![round.clone(), round], Duration::from_secs(1));
+        assert_eq!(results.total.sent, 8);
+        assert_eq!(results.total.per_node.len(), 2);
+        assert_eq!(results.total.per_node["http://a"].sent, 4);
+        assert_eq!(results.total.per_node["http://b"].failed, 2);
+    }
+}
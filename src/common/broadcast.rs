@@ -0,0 +1,76 @@
+/// Transactions shared by the requests of a round broadcasting identical txs
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Mutex,
+};
+
+use crate::config::RoundTemplate;
+
+/// Txs of the rounds broadcasting identical txs, see `config::Round::broadcast_identical`.
+/// The first request of a round generates them, or takes them from its tx source,
+/// and the other requests of the round get a copy.
+pub struct Broadcasts<T> {
+    /// Txs by broadcast seed, with the number of requests of the round yet to get them
+    rounds: Mutex<HashMap<u64, (Vec<T>, usize)>>,
+}
+
+impl<T> Default for Broadcasts<T> {
+    fn default() -> Self {
+        Self {
+            rounds: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> Broadcasts<T> {
+    /// Txs of a request: the ones of its round if it broadcasts, `generate` being only called
+    /// by its first request, or the generated ones otherwise.
+    /// The txs of a round are dropped once all its requests got them.
+    pub fn txs<E>(
+        &self,
+        template: &RoundTemplate,
+        generate: impl FnOnce() -> Result<Vec<T>, E>,
+    ) -> Result<Vec<T>, E> {
+        let Some(seed) = template.broadcast_seed else {
+            return generate();
+        };
+        let mut rounds = self.rounds.lock().unwrap();
+        let (txs, remaining) = match rounds.entry(seed) {
+            Entry::Occupied(round) => round.into_mut(),
+            Entry::Vacant(round) => round.insert((generate()?, template.broadcast_requests.max(1))),
+        };
+        *remaining -= 1;
+        if *remaining > 0 {
+            return Ok(txs.clone());
+        }
+        Ok(rounds.remove(&seed).map(|(txs, _)| txs).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_txs() {
+        let broadcasts = Broadcasts::default();
+        let template = RoundTemplate {
+            broadcast_seed: Some(1),
+            broadcast_requests: 2,
+            ..Default::default()
+        };
+        let mut next = 0;
+        let mut generate = || {
+            next += 1;
+            Ok::<_, ()>(vec![next])
+        };
+        assert_eq!(broadcasts.txs(&template, &mut generate), Ok(vec![1]));
+        assert_eq!(broadcasts.txs(&template, &mut generate), Ok(vec![1]));
+        // all the requests got them, the next round generates its own
+        assert!(broadcasts.rounds.lock().unwrap().is_empty());
+        assert_eq!(broadcasts.txs(&template, &mut generate), Ok(vec![2]));
+
+        let template = RoundTemplate::default();
+        assert_eq!(broadcasts.txs(&template, &mut generate), Ok(vec![3]));
+    }
+}
@@ -0,0 +1,98 @@
+/// Burst scheduler pacing transactions at a target rate
+use std::{future::Future, ops::Range, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use super::RoundResults;
+
+/// Number of bursts per second
+pub const PRECISION: u64 = 10;
+/// Duration of a burst
+pub const BURST_DURATION: Duration = Duration::from_millis(1000 / PRECISION);
+
+/// Number of transactions sent in each burst for the given rate (txs per second)
+pub fn burst_size(rate: u32) -> usize {
+    (rate as usize).div_ceil(PRECISION as usize).max(1)
+}
+
+/// Send `count` transactions at `rate` transactions per second.
+/// The transactions are split into bursts of `burst_size(rate)`, `send` is
+/// called with the range of transactions of each burst and the bursts are
+/// spaced so that the rate is kept. The results of the bursts are merged.
+/// No more bursts are sent once `cancel` is cancelled.
+pub async fn send<F, Fut>(
+    count: usize,
+    rate: u32,
+    cancel: &CancellationToken,
+    mut send: F,
+) -> RoundResults
+where
+    F: FnMut(Range<usize>) -> Fut,
+    Fut: Future<Output = RoundResults>,
+{
+    let burst_size = burst_size(rate);
+    let mut ticker =
+        tokio::time::interval(Duration::from_secs_f64(burst_size as f64 / rate as f64));
+    let mut results = RoundResults::default();
+    for start in (0..count).step_by(burst_size) {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = cancel.cancelled() => {
+                tracing::debug!("Cancelled, {} txs left unsent", count - start);
+                break;
+            }
+        }
+        let burst_results = send(start..(start + burst_size).min(count)).await;
+        results.merge(&burst_results);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_burst_size() {
+        assert_eq!(burst_size(1), 1);
+        assert_eq!(burst_size(100), 10);
+        assert_eq!(burst_size(105), 11);
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let start = Instant::now();
+        let mut bursts = Vec::new();
+        let results = send(25, 100, &CancellationToken::new(), |range| {
+            bursts.push(range.clone());
+            async move {
+                RoundResults {
+                    sent: range.len(),
+                    ..Default::default()
+                }
+            }
+        })
+        .await;
+        assert_eq!(bursts, vec![0..10, 10..20, 20..25]);
+        assert_eq!(results.sent, 25);
+        assert!(start.elapsed() >= 2 * BURST_DURATION);
+    }
+
+    #[tokio::test]
+    async fn test_send_cancelled() {
+        let cancel = CancellationToken::new();
+        let results = send(100, 10, &cancel, |range| {
+            if range.start > 0 {
+                cancel.cancel();
+            }
+            async move {
+                RoundResults {
+                    sent: range.len(),
+                    ..Default::default()
+                }
+            }
+        })
+        .await;
+        assert_eq!(results.sent, 2);
+    }
+}
@@ -0,0 +1,261 @@
+/// Generators of the payloads of the transactions, independent of the adapter sending them
+use rand::{rngs::StdRng, Rng as _};
+use serde_yaml::Value;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use super::TestrpcError;
+use crate::config::{self, RoundTemplate};
+
+/// Generator of the payloads of the transactions of a request
+pub trait PayloadGenerator: Send {
+    /// Payload of the `index`th transaction of the request, of `size` bytes
+    fn generate(&mut self, index: usize, size: usize) -> Vec<u8>;
+}
+
+/// Random bytes, reproducible if seeded
+pub struct Random(pub Option<StdRng>);
+
+impl PayloadGenerator for Random {
+    fn generate(&mut self, _index: usize, size: usize) -> Vec<u8> {
+        let mut payload = vec![0u8; size];
+        match self.0.as_mut() {
+            Some(rng) => rng.fill(&mut payload[..]),
+            None => rand::rng().fill(&mut payload[..]),
+        }
+        payload
+    }
+}
+
+/// Zeroed bytes
+pub struct Zero;
+
+impl PayloadGenerator for Zero {
+    fn generate(&mut self, _index: usize, size: usize) -> Vec<u8> {
+        vec![0u8; size]
+    }
+}
+
+/// Byte `k` of the `i`th payload is `i + k` (wrapping)
+pub struct Incremental;
+
+impl PayloadGenerator for Incremental {
+    fn generate(&mut self, index: usize, size: usize) -> Vec<u8> {
+        (0..size).map(|k| (index + k) as u8).collect()
+    }
+}
+
+/// Payloads of a file, taken in order across the requests and cycling at the end of the file.
+/// They are truncated or zero padded to the requested size.
+pub struct FromFile {
+    payloads: Arc<Vec<Vec<u8>>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl PayloadGenerator for FromFile {
+    fn generate(&mut self, _index: usize, size: usize) -> Vec<u8> {
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut payload = self.payloads[next % self.payloads.len()].clone();
+        payload.resize(size, 0);
+        payload
+    }
+}
+
+/// Payloads of another generator, starting with a prefix taking the place of their first bytes
+pub struct Prefixed {
+    prefix: Arc<Vec<u8>>,
+    inner: Box<dyn PayloadGenerator>,
+}
+
+impl PayloadGenerator for Prefixed {
+    fn generate(&mut self, index: usize, size: usize) -> Vec<u8> {
+        let prefix = &self.prefix[..self.prefix.len().min(size)];
+        let mut payload = prefix.to_vec();
+        payload.extend(self.inner.generate(index, size - prefix.len()));
+        payload
+    }
+}
+
+/// Prefix of the generated payloads, parsed from `args.tx_prefix` (hex), e.g. a tag
+/// to recognize the txs of testrpc in a shared mempool. Empty if not set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TxPrefix(pub Arc<Vec<u8>>);
+
+impl TxPrefix {
+    pub fn from_args(args: &HashMap<String, Value>) -> Result<Self, TestrpcError> {
+        match args.get("tx_prefix") {
+            Some(Value::String(prefix)) => hex::decode(prefix.trim_start_matches("0x"))
+                .map(|prefix| TxPrefix(Arc::new(prefix)))
+                .map_err(|e| TestrpcError::MissingArgs(format!("tx_prefix must be hex: {e}"))),
+            Some(_) => Err(TestrpcError::MissingArgs(
+                "tx_prefix must be a hex string".to_string(),
+            )),
+            None => Ok(TxPrefix::default()),
+        }
+    }
+
+    /// Number of bytes of the prefix
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Generator of the payloads of `generator` starting with the prefix, the payloads keeping their size
+    pub fn prefixed(&self, generator: Box<dyn PayloadGenerator>) -> Box<dyn PayloadGenerator> {
+        if self.is_empty() {
+            return generator;
+        }
+        Box::new(Prefixed {
+            prefix: self.0.clone(),
+            inner: generator,
+        })
+    }
+
+    /// Check that the prefix fits in the generated txs of the template, the replayed ones aren't prefixed
+    pub fn validate(&self, template: &RoundTemplate) -> Result<(), TestrpcError> {
+        if template.tx_source.is_none() && self.len() > template.tx_size {
+            return Err(TestrpcError::LoadRoundTemplateError(format!(
+                "tx_prefix of {} bytes doesn't fit in txs of {} bytes",
+                self.len(),
+                template.tx_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Generator selected by `args.payload_generator`, creating the generator of each request
+#[derive(Debug, Clone, Default)]
+pub enum PayloadGenerators {
+    #[default]
+    Random,
+    Zero,
+    Incremental,
+    /// Payloads read once from a file, in the format of the tx files (see `config::read_tx_file`)
+    FromFile {
+        payloads: Arc<Vec<Vec<u8>>>,
+        next: Arc<AtomicUsize>,
+    },
+}
+
+impl PayloadGenerators {
+    /// Parse `args.payload_generator`: `random` (default), `zero`, `incremental` or `file: <path>`.
+    /// `payload_mode` is a deprecated alias.
+    pub fn from_args(args: &HashMap<String, Value>) -> Result<Self, TestrpcError> {
+        let invalid = || {
+            TestrpcError::MissingArgs(
+                "payload_generator must be one of random, incremental, zero, or file: <path>"
+                    .to_string(),
+            )
+        };
+        let generator = match args.get("payload_generator").or(args.get("payload_mode")) {
+            Some(generator) => generator,
+            None => return Ok(PayloadGenerators::default()),
+        };
+        match generator {
+            Value::String(name) => match name.as_str() {
+                "random" => Ok(PayloadGenerators::Random),
+                "zero" => Ok(PayloadGenerators::Zero),
+                "incremental" => Ok(PayloadGenerators::Incremental),
+                _ => Err(invalid()),
+            },
+            Value::Mapping(generator) => match generator.get("file") {
+                Some(Value::String(path)) => Ok(PayloadGenerators::FromFile {
+                    payloads: Arc::new(config::read_tx_file(path)?),
+                    next: Arc::new(AtomicUsize::new(0)),
+                }),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Whether the payloads are random, only those can be pooled
+    pub fn is_random(&self) -> bool {
+        matches!(self, PayloadGenerators::Random)
+    }
+
+    /// Generator of the payloads of a request, `rng` seeding the random ones
+    pub fn for_request(&self, rng: Option<StdRng>) -> Box<dyn PayloadGenerator> {
+        match self {
+            PayloadGenerators::Random => Box::new(Random(rng)),
+            PayloadGenerators::Zero => Box::new(Zero),
+            PayloadGenerators::Incremental => Box::new(Incremental),
+            PayloadGenerators::FromFile { payloads, next } => Box::new(FromFile {
+                payloads: payloads.clone(),
+                next: next.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng as _;
+
+    fn generators(generator: Value) -> Result<PayloadGenerators, TestrpcError> {
+        PayloadGenerators::from_args(&HashMap::from([(
+            "payload_generator".to_string(),
+            generator,
+        )]))
+    }
+
+    #[test]
+    fn test_generators() {
+        let mut generator = generators(Value::from("incremental"))
+            .unwrap()
+            .for_request(None);
+        assert_eq!(generator.generate(1, 3), vec![1, 2, 3]);
+        let mut generator = generators(Value::from("zero")).unwrap().for_request(None);
+        assert_eq!(generator.generate(1, 2), vec![0, 0]);
+        assert!(generators(Value::from("other")).is_err());
+
+        let seeded = || {
+            PayloadGenerators::Random
+                .for_request(Some(StdRng::seed_from_u64(1)))
+                .generate(0, 8)
+        };
+        assert_eq!(seeded(), seeded());
+
+        let path = std::env::temp_dir().join(format!("testrpc-payloads-{}", std::process::id()));
+        std::fs::write(&path, "0102\n03\n").unwrap();
+        let file = serde_yaml::from_str(&format!("file: {}", path.display())).unwrap();
+        let generators = generators(file).unwrap();
+        let mut generator = generators.for_request(None);
+        assert_eq!(generator.generate(0, 2), vec![1, 2]);
+        assert_eq!(generator.generate(1, 2), vec![3, 0]);
+        // the file is shared by the requests
+        assert_eq!(generators.for_request(None).generate(0, 1), vec![1]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tx_prefix() {
+        let args = HashMap::from([("tx_prefix".to_string(), Value::from("0xcafe"))]);
+        let prefix = TxPrefix::from_args(&args).unwrap();
+        let mut generator = prefix.prefixed(PayloadGenerators::Incremental.for_request(None));
+        // the incremental part is shortened to keep the size
+        assert_eq!(generator.generate(1, 4), vec![0xca, 0xfe, 1, 2]);
+        assert_eq!(generator.generate(0, 1), vec![0xca]);
+
+        let template = RoundTemplate {
+            txs: 1,
+            tx_size: 1,
+            ..Default::default()
+        };
+        assert!(prefix.validate(&template).is_err());
+        assert!(TxPrefix::default().validate(&template).is_ok());
+
+        let args = HashMap::from([("tx_prefix".to_string(), Value::from("xyz"))]);
+        assert!(TxPrefix::from_args(&args).is_err());
+    }
+}
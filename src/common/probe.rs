@@ -0,0 +1,106 @@
+/// Minimal HTTP server of the probes of a running process (health checks, metrics)
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use super::TestrpcError;
+use crate::ctx;
+
+pub const NOT_FOUND: &str =
+    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// Serve the given address until the context is stopped, answering each `GET` request with
+/// the response `route` returns for its path, 404 if None.
+/// `name` tells the probes apart in the logs.
+pub async fn serve<F>(
+    ctx: Arc<ctx::Context>,
+    addr: &str,
+    name: &'static str,
+    route: F,
+) -> Result<JoinHandle<()>, TestrpcError>
+where
+    F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| TestrpcError::ServerError(format!("Failed to bind {addr}: {e}")))?;
+    let route = Arc::new(route);
+    let mut quit = ctx.recv();
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_connection(stream, name, route.clone()));
+                    }
+                    Err(e) => tracing::warn!("Failed to accept {} connection: {}", name, e),
+                },
+                _ = quit.recv() => {
+                    tracing::debug!("{} server stopped", name);
+                    break;
+                }
+            }
+        }
+    }))
+}
+
+/// Response with the given status line (e.g. `200 OK`), content type and body
+pub fn response(status: &str, content_type: Option<&str>, body: &str) -> String {
+    let content_type = content_type
+        .map(|content_type| format!("Content-Type: {content_type}\r\n"))
+        .unwrap_or_default();
+    format!(
+        "HTTP/1.1 {status}\r\n{content_type}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Answer a single HTTP request on the given connection
+async fn handle_connection(
+    mut stream: TcpStream,
+    name: &str,
+    route: Arc<impl Fn(&str) -> Option<String>>,
+) {
+    let mut buf = [0u8; 1024];
+    let n = match stream.read(&mut buf).await {
+        Ok(n) => n,
+        Err(e) => {
+            tracing::debug!("Failed to read {} request: {}", name, e);
+            return;
+        }
+    };
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let response = get_path(&request)
+        .and_then(|path| route(path))
+        .unwrap_or_else(|| NOT_FOUND.to_string());
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        tracing::debug!("Failed to write {} response: {}", name, e);
+    }
+}
+
+/// Path of a `GET` request
+fn get_path(request: &str) -> Option<&str> {
+    request.strip_prefix("GET ")?.split(' ').next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_path() {
+        assert_eq!(get_path("GET /healthz HTTP/1.1\r\n"), Some("/healthz"));
+        assert_eq!(get_path("POST /healthz HTTP/1.1\r\n"), None);
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(
+            response("200 OK", Some("text/plain"), "ok"),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+        );
+    }
+}
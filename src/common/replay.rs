@@ -0,0 +1,70 @@
+/// Replay of the transactions of a tx file, see `config::TxSource`
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use super::TestrpcError;
+use crate::config::{self, TxSource};
+
+/// Transactions of a tx file, taken in order and cycling at the end of the file
+struct TxFile {
+    txs: Vec<Vec<u8>>,
+    next: AtomicUsize,
+}
+
+/// Tx files by path, read on first use and shared by the requests of a run
+#[derive(Default)]
+pub struct TxFiles {
+    files: Mutex<HashMap<String, Arc<TxFile>>>,
+}
+
+impl TxFiles {
+    /// Take the next `count` transactions of the source, the file is read on first use
+    pub fn take(&self, source: &TxSource, count: usize) -> Result<Vec<Vec<u8>>, TestrpcError> {
+        let TxSource::File { path } = source;
+        let file = {
+            let mut files = self.files.lock().unwrap();
+            match files.get(path) {
+                Some(file) => file.clone(),
+                None => {
+                    let file = Arc::new(TxFile {
+                        txs: config::read_tx_file(path)?,
+                        next: AtomicUsize::new(0),
+                    });
+                    files.insert(path.clone(), file.clone());
+                    file
+                }
+            }
+        };
+        let first = file.next.fetch_add(count, Ordering::Relaxed);
+        Ok((first..first + count)
+            .map(|i| file.txs[i % file.txs.len()].clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take() {
+        let path = std::env::temp_dir().join(format!("testrpc-replay-{}", std::process::id()));
+        std::fs::write(&path, "01\n02\n03\n").unwrap();
+        let source = TxSource::File {
+            path: path.to_string_lossy().to_string(),
+        };
+        let files = TxFiles::default();
+        assert_eq!(files.take(&source, 2).unwrap(), vec![vec![1], vec![2]]);
+        // cycles through the file
+        assert_eq!(
+            files.take(&source, 3).unwrap(),
+            vec![vec![3], vec![1], vec![2]]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
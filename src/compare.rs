@@ -0,0 +1,163 @@
+/// Comparison of the results of two runs, to tell the regressions between them
+use std::path::Path;
+
+use crate::common::{FlowResults, TestrpcError};
+
+/// Change of a metric between two runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delta {
+    pub metric: &'static str,
+    pub before: f64,
+    pub after: f64,
+    /// Whether a higher value is better (e.g. sent txs) or worse (e.g. failed txs, latency)
+    pub higher_is_better: bool,
+}
+
+impl Delta {
+    /// Relative change from before to after, None if it is infinite (from 0 to nonzero)
+    pub fn change(&self) -> Option<f64> {
+        match (self.before, self.after) {
+            (before, after) if before == after => Some(0.0),
+            (0.0, _) => None,
+            (before, after) => Some((after - before) / before),
+        }
+    }
+
+    /// Whether the metric got worse by more than the tolerance, a ratio of the value before
+    pub fn is_regression(&self, tolerance: f64) -> bool {
+        let worse = if self.higher_is_better {
+            self.after < self.before
+        } else {
+            self.after > self.before
+        };
+        worse && self.change().is_none_or(|change| change.abs() > tolerance)
+    }
+}
+
+/// Load results serialized as JSON or YAML (e.g. by `--output-file`)
+pub fn load_results(path: &str) -> Result<FlowResults, TestrpcError> {
+    let raw = std::fs::read_to_string(Path::new(path))
+        .map_err(|e| TestrpcError::OutputError(format!("Failed to read {path}: {e}")))?;
+    // YAML being a superset of JSON, both are parsed as YAML
+    serde_yaml::from_str(&raw)
+        .map_err(|e| TestrpcError::OutputError(format!("Invalid results in {path}: {e}")))
+}
+
+/// Changes of the totals between the results of two runs.
+/// The latency percentiles (in milliseconds) are only compared if both runs have latencies.
+pub fn compare(before: &FlowResults, after: &FlowResults) -> Vec<Delta> {
+    let delta = |metric, before, after, higher_is_better| Delta {
+        metric,
+        before,
+        after,
+        higher_is_better,
+    };
+    let mut deltas = vec![
+        delta(
+            "sent",
+            before.total.sent as f64,
+            after.total.sent as f64,
+            true,
+        ),
+        delta(
+            "failed",
+            before.total.failed as f64,
+            after.total.failed as f64,
+            false,
+        ),
+        delta(
+            "bytes_sent",
+            before.total.bytes_sent as f64,
+            after.total.bytes_sent as f64,
+            true,
+        ),
+        delta(
+            "bytes_failed",
+            before.total.bytes_failed as f64,
+            after.total.bytes_failed as f64,
+            false,
+        ),
+    ];
+    if let (Some(before), Some(after)) = (&before.total.latency, &after.total.latency) {
+        let ms = |latency: std::time::Duration| latency.as_secs_f64() * 1000.0;
+        deltas.extend([
+            delta("latency_p50", ms(before.p50), ms(after.p50), false),
+            delta("latency_p95", ms(before.p95), ms(after.p95), false),
+            delta("latency_p99", ms(before.p99), ms(after.p99), false),
+        ]);
+    }
+    deltas
+}
+
+/// Table of the deltas, each one marked as a regression or not given the tolerance
+pub fn format_comparison(deltas: &[Delta], tolerance: f64) -> String {
+    let mut table = format!(
+        "{:<12}  {:>12}  {:>12}  {:>9}  VERDICT\n",
+        "METRIC", "BEFORE", "AFTER", "CHANGE"
+    );
+    for delta in deltas {
+        let change = match delta.change() {
+            Some(change) => format!("{:+.1}%", change * 100.0),
+            None => "+inf".to_string(),
+        };
+        let verdict = if delta.is_regression(tolerance) {
+            "FAIL"
+        } else {
+            "ok"
+        };
+        table.push_str(&format!(
+            "{:<12}  {:>12.2}  {:>12.2}  {change:>9}  {verdict}\n",
+            delta.metric, delta.before, delta.after
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::RoundResults;
+    use std::time::Duration;
+
+    fn results(sent: usize, failed: usize, latency_ms: u64) -> FlowResults {
+        let mut round = RoundResults {
+            sent,
+            failed,
+            latencies: vec![Duration::from_millis(latency_ms)],
+            ..Default::default()
+        };
+        round.compute_latency_stats();
+        FlowResults::new_from_round_results(vec![round], Duration::from_secs(1))
+    }
+
+    #[test]
+    fn test_compare() {
+        let deltas = compare(&results(100, 0, 10), &results(95, 2, 10));
+        let delta = |metric| deltas.iter().find(|delta| delta.metric == metric).unwrap();
+        assert_eq!(delta("sent").change(), Some(-0.05));
+        assert!(!delta("sent").is_regression(0.1));
+        assert!(delta("sent").is_regression(0.01));
+        // from no failure to some, whatever the tolerance
+        assert_eq!(delta("failed").change(), None);
+        assert!(delta("failed").is_regression(10.0));
+        assert!(!delta("latency_p50").is_regression(0.0));
+
+        // improvements are never regressions
+        let deltas = compare(&results(95, 2, 20), &results(100, 0, 10));
+        assert!(deltas.iter().all(|delta| !delta.is_regression(0.0)));
+
+        let table = format_comparison(&deltas, 0.0);
+        assert!(table.starts_with("METRIC"), "{table}");
+        assert!(table.contains("-50.0%"), "{table}");
+    }
+
+    #[test]
+    fn test_load_results() {
+        let path = std::env::temp_dir().join("testrpc_test_load_results.json");
+        let results = results(1, 0, 1);
+        std::fs::write(&path, serde_json::to_string(&results).unwrap()).unwrap();
+        let loaded = load_results(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.total.sent, 1);
+        assert!(load_results("does/not/exist.json").is_err());
+    }
+}
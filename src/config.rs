@@ -1,7 +1,16 @@
 use crate::common::TestrpcError;
+use crate::{jrpc, rng};
+use base64::Engine as _;
+use rand::{Rng as _, SeedableRng as _};
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
-use std::{collections::HashMap, fmt::Display, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
 /// AdapterConfig to use for the test flow.
 /// The adapter is responsible for providing the actual implementation of the test flow for sending rpcs.
@@ -9,6 +18,8 @@ use std::{collections::HashMap, fmt::Display, str::FromStr};
 #[serde(rename_all = "lowercase")]
 pub enum AdapterConfig {
     Hotshot,
+    Grpc,
+    Eth,
     Libp2p, // TODO: Implement libp2p adapter
 }
 
@@ -18,6 +29,8 @@ impl FromStr for AdapterConfig {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "hotshot" => Ok(AdapterConfig::Hotshot),
+            "grpc" => Ok(AdapterConfig::Grpc),
+            "eth" => Ok(AdapterConfig::Eth),
             "libp2p" => Ok(AdapterConfig::Libp2p),
             _ => Err(TestrpcError::UnsupportedAdapter(s.to_string())),
         }
@@ -31,6 +44,8 @@ impl Display for AdapterConfig {
             "{}",
             match self {
                 AdapterConfig::Hotshot => "hotshot",
+                AdapterConfig::Grpc => "grpc",
+                AdapterConfig::Eth => "eth",
                 AdapterConfig::Libp2p => "libp2p",
             }
         )
@@ -39,8 +54,13 @@ impl Display for AdapterConfig {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
-    /// Interval between rounds in milliseconds
+    /// Interval between rounds in seconds, 0 to run them back to back (see `back_to_back`)
     pub interval: u64,
+    /// Allow a zero `interval`, running the rounds back to back
+    #[serde(default)]
+    pub back_to_back: bool,
+    /// Maximum random delay in milliseconds added to each interval, to spread the load
+    pub interval_jitter: Option<u64>,
     /// Number of iterations to run, will run indefinitely if None
     pub iterations: Option<usize>,
     /// Number of expected nodes
@@ -53,29 +73,411 @@ pub struct Config {
     pub args: HashMap<String, Value>,
     /// RPCs to use for sending transactions
     pub rpcs: Option<Vec<String>>,
+    /// File listing the RPCs, one per line or as a JSON array, instead of `rpcs`
+    pub rpcs_file: Option<String>,
     /// Rounds declaration
     pub rounds: Vec<Round>,
     /// Timeout for each RPC request in seconds
     /// If None, defaults to 15 seconds
     pub timeout: Option<u32>,
+    /// Ramp up the number of txs per iteration, overriding the txs of the templates
+    pub ramp: Option<RampConfig>,
+    /// Back off when the rounds fail, scaling down the txs of the next iterations
+    pub adaptive: Option<AdaptiveConfig>,
+    /// Number of initial iterations to run as warmup, excluded from the results.
+    /// The warmup iterations are part of `iterations`.
+    pub warmup: Option<usize>,
+    /// Number of rounds in flight at once, the next round being launched after the interval
+    /// without waiting for the previous ones to complete. One round at a time if None
+    pub pipeline_depth: Option<usize>,
+    /// Maximum duration of the whole flow in seconds, regardless of `iterations`
+    pub max_duration: Option<u64>,
+    /// Time given to the in-flight rounds to complete once the flow is stopped (e.g. "5s"),
+    /// they are aborted afterwards and their txs counted as failed. Waits for them if None
+    #[serde(default, with = "humantime_serde")]
+    pub shutdown_grace: Option<Duration>,
+    /// Remove the endpoints that don't answer the initial ping before running the rounds.
+    /// `num_of_nodes` is checked against all the endpoints, before the unreachable ones are removed.
+    #[serde(default)]
+    pub skip_unreachable: bool,
+    /// Fail if the number of endpoints differs from `num_of_nodes` (the default),
+    /// otherwise warn and run with the available endpoints
+    #[serde(default = "default_strict_node_count")]
+    pub strict_node_count: bool,
+    /// Fetch the status of the nodes of each round once it completes, see `Adapter::node_status`
+    #[serde(default)]
+    pub collect_status: bool,
+    /// Template of the rounds declaring neither `template` nor `use_template`,
+    /// defaults to 1 tx of 100 bytes
+    pub default_template: Option<RoundTemplate>,
+    /// Dry run the flow (`noop` or `realistic`), the requests aren't sent. Sent by default,
+    /// the binary overrides it with `--dry-run` or the `DRY_RUN` environment variable
+    #[serde(default)]
+    pub dry_run: Option<jrpc::DryRun>,
 }
 
+fn default_strict_node_count() -> bool {
+    true
+}
+
+/// Number of txs of the default template
+const DEFAULT_TEMPLATE_TXS: usize = 1;
+/// Size of the txs of the default template
+const DEFAULT_TEMPLATE_TX_SIZE: usize = 100;
+/// Longest delay drawn between two txs, the exponential delays of a tiny `lambda` being unbounded
+const MAX_ARRIVAL_DELAY: Duration = Duration::from_secs(3600);
+
+impl Config {
+    /// Interval to wait before the next round, randomized within
+    /// `[interval, interval + interval_jitter]`
+    pub fn next_interval(&self) -> Duration {
+        let interval = Duration::from_secs(self.interval);
+        match self.interval_jitter {
+            Some(jitter) if jitter > 0 => {
+                interval + Duration::from_millis(rng::with_rng(|rng| rng.random_range(0..=jitter)))
+            }
+            _ => interval,
+        }
+    }
+
+    /// Get the template of the round, falling back to `default_template` if the round declares none
+    pub fn round_template(&self, round: &Round) -> Option<RoundTemplate> {
+        round
+            .get_template(self.round_templates.clone())
+            .or_else(|| {
+                if round.use_template.is_some() {
+                    return None;
+                }
+                Some(
+                    self.default_template
+                        .clone()
+                        .unwrap_or_else(|| RoundTemplate {
+                            txs: DEFAULT_TEMPLATE_TXS,
+                            tx_size: DEFAULT_TEMPLATE_TX_SIZE,
+                            ..Default::default()
+                        }),
+                )
+            })
+    }
+
+    /// Keep only the rpcs for which `keep` (parallel to the rpcs) is true, remapping the
+    /// rpc indices of the rounds. Rounds left without rpcs are removed.
+    /// Returns the number of removed rounds.
+    pub fn retain_rpcs(&mut self, keep: &[bool]) -> usize {
+        let mut new_indices = Vec::with_capacity(keep.len());
+        let mut next = 0;
+        for keep in keep {
+            new_indices.push(keep.then_some(next));
+            if *keep {
+                next += 1;
+            }
+        }
+        if let Some(rpcs) = self.rpcs.as_mut() {
+            let mut kept = keep.iter();
+            rpcs.retain(|_| *kept.next().unwrap_or(&false));
+        }
+        for round in self.rounds.iter_mut() {
+            let weights = (0..round.rpcs.len())
+                .map(|i| round.weight(i))
+                .collect::<Vec<_>>();
+            let (rpcs, kept_weights): (Vec<_>, Vec<_>) = round
+                .rpcs
+                .iter()
+                .zip(weights)
+                .filter_map(|(rpc, weight)| {
+                    new_indices
+                        .get(*rpc)
+                        .copied()
+                        .flatten()
+                        .map(|rpc| (rpc, weight))
+                })
+                .unzip();
+            round.rpcs = rpcs;
+            if round.weights.is_some() {
+                round.weights = Some(kept_weights);
+            }
+        }
+        let num_rounds = self.rounds.len();
+        self.rounds.retain(|round| !round.rpcs.is_empty());
+        num_rounds - self.rounds.len()
+    }
+
+    /// Select all the `num_of_rpcs` rpcs in the rounds declared with `to_all`
+    pub fn expand_to_all(&mut self, num_of_rpcs: usize) {
+        for round in self.rounds.iter_mut().filter(|round| round.to_all) {
+            round.rpcs = (0..num_of_rpcs).collect();
+        }
+    }
+
+    /// Remove the rpc indices that are out of the `available` endpoints from the rounds,
+    /// rounds left without rpcs are removed. Returns the number of removed rounds.
+    pub fn truncate_rpcs(&mut self, available: usize) -> usize {
+        self.retain_rpcs(&vec![true; available])
+    }
+
+    /// Number of txs that iteration (starting at 1) will send, across all the rpcs of its round
+    pub fn planned_txs(&self, iteration: u32) -> usize {
+        if self.rounds.is_empty() || iteration == 0 {
+            return 0;
+        }
+        let round = self.rounds[(iteration as usize - 1) % self.rounds.len()].sampled(iteration);
+        let txs = match &self.ramp {
+            Some(ramp) => ramp.txs(iteration),
+            None => self
+                .round_template(&round)
+                .map_or(0, |template| template.txs),
+        };
+        (0..round.rpcs.len())
+            .map(|i| txs * round.weight(i) as usize)
+            .sum()
+    }
+
+    /// Total number of txs the flow will send, None if it runs indefinitely
+    pub fn total_planned_txs(&self) -> Option<usize> {
+        let iterations = self.iterations? as u32;
+        Some((1..=iterations).map(|i| self.planned_txs(i)).sum())
+    }
+}
+
+/// Ramp up configuration, the number of txs sent to each rpc grows with the iterations
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RampConfig {
+    /// Number of txs of the first iteration
+    pub start_txs: usize,
+    /// Number of txs added on each iteration
+    pub step: usize,
+    /// Maximum number of txs
+    pub max_txs: usize,
+}
+
+impl RampConfig {
+    /// Get the number of txs for the given iteration (starting at 1)
+    pub fn txs(&self, iteration: u32) -> usize {
+        let steps = iteration.saturating_sub(1) as usize;
+        self.start_txs
+            .saturating_add(self.step.saturating_mul(steps))
+            .min(self.max_txs)
+    }
+}
+
+/// Adaptive load configuration: when the failure ratio of a pass over the rounds exceeds the
+/// threshold, the txs of the next pass are scaled down by the backoff factor, and scaled back up
+/// (up to the configured txs) once the rounds are healthy again
 #[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdaptiveConfig {
+    /// Ratio of failed txs above which a pass over the rounds is considered failing, in `[0, 1)`
+    pub failure_threshold: f64,
+    /// Factor applied to the load on each failing pass, in `(0, 1)`
+    pub backoff_factor: f64,
+}
+
+impl AdaptiveConfig {
+    /// Load (fraction of the configured txs) of the next pass over the rounds, after a pass
+    /// with the given failure ratio
+    pub fn next_load(&self, load: f64, failure_ratio: f64) -> f64 {
+        if failure_ratio > self.failure_threshold {
+            load * self.backoff_factor
+        } else {
+            (load / self.backoff_factor).min(1.0)
+        }
+    }
+
+    /// Number of txs to send at the given load, at least 1
+    pub fn txs(&self, txs: usize, load: f64) -> usize {
+        ((txs as f64 * load).round() as usize).max(1)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct RoundTemplate {
     pub txs: usize,
+    /// Size of each transaction, or the minimum size if `tx_size_max` is set
     pub tx_size: usize,
-    pub latency: Option<String>,
+    /// Maximum size of the transactions, each one getting a random size in `tx_size..=tx_size_max`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_size_max: Option<usize>,
+    /// Delay between transactions (e.g. "50ms"), transactions are sent in one request if None
+    #[serde(default, with = "humantime_serde")]
+    pub latency: Option<Duration>,
+    /// Target send rate in transactions per second, transactions are sent as fast as possible if None
+    pub rate: Option<u32>,
+    /// Source of the transactions, random payloads of `tx_size` bytes if None
+    pub tx_source: Option<TxSource>,
+    /// Distribution of the delays between transactions, sent one by one. Constant `latency` if None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival: Option<ArrivalDist>,
+    /// Name of the template of `round_templates` this one inherits from, its fields being
+    /// overridden by the ones declared here. Resolved when the config is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Seed of the payloads shared by the requests of a round broadcasting identical txs,
+    /// set by the runner for the rounds with `broadcast_identical`
+    #[serde(skip)]
+    pub broadcast_seed: Option<u64>,
+    /// Number of requests of a round broadcasting identical txs, sharing them
+    /// (see `common::broadcast::Broadcasts`), set by the runner along with `broadcast_seed`
+    #[serde(skip)]
+    pub broadcast_requests: usize,
+}
+
+impl RoundTemplate {
+    /// Size of each transaction of a request: `tx_size`, or drawn in `tx_size..=tx_size_max`
+    /// from the seed of the run, the iteration and the request id (randomly without seed).
+    /// The requests of a round broadcasting identical txs draw the same sizes.
+    pub fn tx_sizes(&self, iteration: u32, req_id: u64) -> Vec<usize> {
+        let Some(max) = self.tx_size_max.filter(|max| *max > self.tx_size) else {
+            return vec![self.tx_size; self.txs];
+        };
+        let mut rng = match (self.broadcast_seed, rng::seed()) {
+            (Some(seed), _) => rng::for_request(seed, iteration, u64::MAX),
+            (None, Some(seed)) => rng::for_request(seed, iteration, req_id),
+            (None, None) => rand::rngs::StdRng::from_rng(&mut rand::rng()),
+        };
+        (0..self.txs)
+            .map(|_| rng.random_range(self.tx_size..=max))
+            .collect()
+    }
+
+    /// Delays before each transaction of a request but the first, per `arrival` and `latency`.
+    /// Drawn from the seed of the run, the iteration and the request id (randomly without seed).
+    /// None if the transactions aren't sent one by one.
+    pub fn arrival_delays(&self, iteration: u32, req_id: u64) -> Option<Vec<Duration>> {
+        let count = self.txs.saturating_sub(1);
+        let rng = || match rng::seed() {
+            // a stream apart from the one of the tx sizes
+            Some(seed) => rng::for_request(seed.wrapping_add(1), iteration, req_id),
+            None => rand::rngs::StdRng::from_rng(&mut rand::rng()),
+        };
+        match self.arrival {
+            None | Some(ArrivalDist::Constant) => self.latency.map(|latency| vec![latency; count]),
+            Some(ArrivalDist::Poisson { lambda }) => {
+                let mut rng = rng();
+                Some(
+                    (0..count)
+                        .map(|_| {
+                            let secs = -(1.0 - rng.random::<f64>()).ln() / lambda;
+                            Duration::try_from_secs_f64(secs)
+                                .map_or(MAX_ARRIVAL_DELAY, |delay| delay.min(MAX_ARRIVAL_DELAY))
+                        })
+                        .collect(),
+                )
+            }
+            Some(ArrivalDist::Uniform { min, max }) => {
+                let mut rng = rng();
+                Some((0..count).map(|_| rng.random_range(min..=max)).collect())
+            }
+        }
+    }
+
+    /// Rng of the random payloads of a request: the broadcast seed of the round if it broadcasts
+    /// identical txs, otherwise `seed` mixed with the iteration and the request id.
+    /// None for unseeded payloads.
+    pub fn payload_rng(
+        &self,
+        seed: Option<u64>,
+        iteration: u32,
+        req_id: u64,
+    ) -> Option<rand::rngs::StdRng> {
+        match self.broadcast_seed {
+            Some(seed) => Some(rand::rngs::StdRng::seed_from_u64(seed)),
+            None => seed.map(|seed| rng::for_request(seed, iteration, req_id)),
+        }
+    }
+
+    /// Mean size of the transactions
+    pub fn mean_tx_size(&self) -> usize {
+        match self.tx_size_max {
+            Some(max) if max > self.tx_size => (self.tx_size + max) / 2,
+            _ => self.tx_size,
+        }
+    }
+}
+
+/// Distribution of the delays between the transactions of a request
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ArrivalDist {
+    /// The constant `latency` of the template
+    Constant,
+    /// Poisson process of `lambda` transactions per second, the delays being exponential
+    Poisson { lambda: f64 },
+    /// Delays drawn uniformly in `min..=max` (e.g. "10ms")
+    Uniform {
+        #[serde(with = "humantime_serde")]
+        min: Duration,
+        #[serde(with = "humantime_serde")]
+        max: Duration,
+    },
+}
+
+/// Source of pre-built transactions, replayed instead of generating payloads
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TxSource {
+    /// File with one hex (optionally `0x` prefixed) or base64 encoded transaction per line.
+    /// The transactions are sent in order, cycling through the file to reach `txs`.
+    /// `tx_size` is ignored.
+    File { path: String },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Round {
+    #[serde(default)]
     pub rpcs: Vec<usize>,
+    /// Send to every rpc instead of listing them in `rpcs`, expanded once the rpcs are known
+    #[serde(default)]
+    pub to_all: bool,
     pub repeat: Option<usize>,
     pub template: Option<RoundTemplate>,
     pub use_template: Option<String>,
+    /// Weight of each rpc (parallel to `rpcs`), scaling the number of txs sent to it
+    pub weights: Option<Vec<u32>>,
+    /// Whether the rpcs are sent to at the same time (the default), or one after the other
+    pub concurrent: Option<bool>,
+    /// Fraction (in (0, 1]) of the rpcs randomly selected on each iteration of the round
+    pub sample: Option<f32>,
+    /// Send the same txs to every rpc of the round, e.g. to test the detection of duplicates.
+    /// The requests keep their own ids, so that the servers can still tell them apart.
+    #[serde(default)]
+    pub broadcast_identical: bool,
 }
 
 impl Round {
+    /// Whether the rpcs of the round are sent to concurrently, defaults to true
+    pub fn is_concurrent(&self) -> bool {
+        self.concurrent.unwrap_or(true)
+    }
+
+    /// The round with the rpcs selected for the given iteration: if the round samples, a random
+    /// `sample` fraction of `rpcs` (rounded up), drawn from the seed of the run
+    pub fn sampled(&self, iteration: u32) -> Round {
+        let Some(sample) = self.sample.filter(|_| !self.rpcs.is_empty()) else {
+            return self.clone();
+        };
+        let amount = ((self.rpcs.len() as f32 * sample).ceil() as usize).clamp(1, self.rpcs.len());
+        let mut indices =
+            rand::seq::index::sample(&mut rng::for_iteration(iteration), self.rpcs.len(), amount)
+                .into_vec();
+        indices.sort_unstable();
+        Round {
+            rpcs: indices.iter().map(|i| self.rpcs[*i]).collect(),
+            weights: self
+                .weights
+                .as_ref()
+                .map(|_| indices.iter().map(|i| self.weight(*i)).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Get the weight of the rpc at the given position in `rpcs`, defaults to 1
+    pub fn weight(&self, i: usize) -> u32 {
+        self.weights
+            .as_ref()
+            .and_then(|weights| weights.get(i))
+            .copied()
+            .unwrap_or(1)
+    }
+
     /// Get the template for the round
     pub fn get_template(
         &self,
@@ -94,16 +496,1207 @@ impl Round {
     }
 }
 
+/// Load the config file, expanding the environment variables it references (see `expand_env`).
+/// The format is detected from the extension: `.toml`, `.json`, or YAML for any other extension.
+/// If the file declares `extends: <path>`, it is merged over that base config (see `merge_values`),
+/// the path being relative to the directory of the file.
 pub fn load_config(f: &str) -> Result<Config, TestrpcError> {
-    let config = std::fs::read_to_string(f)
-        .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), f.to_string()))?;
-    let config: Config = serde_yaml::from_str(config.as_str())
+    let mut config = load_config_value(Path::new(f), &mut Vec::new())?;
+    resolve_template_extends(&mut config)
+        .map_err(|e| TestrpcError::LoadConfigError(e, f.to_string()))?;
+    let config: Config = serde_yaml::from_value(config)
         .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), f.to_string()))?;
+    check_rounds(&config).map_err(|e| TestrpcError::LoadConfigError(e, f.to_string()))?;
     Ok(config)
 }
 
+/// Load the config file, merged over the base configs it extends.
+/// `chain` holds the files being loaded, to detect cyclic extends.
+fn load_config_value(path: &Path, chain: &mut Vec<PathBuf>) -> Result<Value, TestrpcError> {
+    let err = |e: String| TestrpcError::LoadConfigError(e, path.display().to_string());
+    let canonical = path.canonicalize().map_err(|e| err(e.to_string()))?;
+    if chain.contains(&canonical) {
+        return Err(err(format!(
+            "cyclic extends: {}",
+            chain
+                .iter()
+                .chain([&canonical])
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        )));
+    }
+    let raw = std::fs::read_to_string(path).map_err(|e| err(e.to_string()))?;
+    let mut config = parse_config_value(path, &raw).map_err(err)?;
+    expand_env(&mut config, &|name| std::env::var(name).ok()).map_err(err)?;
+    let extends = match config
+        .as_mapping_mut()
+        .and_then(|config| config.remove("extends"))
+    {
+        Some(Value::String(extends)) => extends,
+        Some(_) => return Err(err("extends must be a path".to_string())),
+        None => return Ok(config),
+    };
+    let base_path = path.parent().unwrap_or(Path::new("")).join(extends);
+    chain.push(canonical);
+    let base = load_config_value(&base_path, chain)?;
+    chain.pop();
+    Ok(merge_values(base, config))
+}
+
+/// Parse the raw config in the format given by the extension of its path
+fn parse_config_value(path: &Path, raw: &str) -> Result<Value, String> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(raw).map_err(|e| e.to_string()),
+        Some("json") => serde_json::from_str(raw).map_err(|e| e.to_string()),
+        _ => serde_yaml::from_str(raw).map_err(|e| e.to_string()),
+    }
+}
+
+/// Merge the templates declaring `extends: <name>` (in `round_templates` or inline in the rounds)
+/// over the template of `round_templates` they extend, see `merge_values`.
+/// Errors on an unknown template or cyclic extends.
+fn resolve_template_extends(config: &mut Value) -> Result<(), String> {
+    let templates = config
+        .get("round_templates")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+    let mut resolved = HashMap::new();
+    for name in templates.keys().filter_map(Value::as_str) {
+        resolve_template(name, &templates, &mut resolved, &mut Vec::new())?;
+    }
+    if let Some(round_templates) = config
+        .get_mut("round_templates")
+        .and_then(Value::as_mapping_mut)
+    {
+        for (name, template) in round_templates.iter_mut() {
+            if let Some(resolved) = name.as_str().and_then(|name| resolved.get(name)) {
+                *template = Value::clone(resolved);
+            }
+        }
+    }
+    let rounds = config
+        .get_mut("rounds")
+        .and_then(Value::as_sequence_mut)
+        .into_iter()
+        .flatten();
+    for (r, round) in rounds.enumerate() {
+        let Some(template) = round.get_mut("template") else {
+            continue;
+        };
+        let Some(base) = template.get("extends").and_then(Value::as_str) else {
+            continue;
+        };
+        let base = resolved.get(base).ok_or(format!(
+            "Round {r}: template extends unknown template '{base}'"
+        ))?;
+        *template = merge_values(base.clone(), template.clone());
+    }
+    Ok(())
+}
+
+/// Resolve the named template of `templates` over the chain of templates it extends.
+/// `chain` holds the templates being resolved, to detect cyclic extends.
+fn resolve_template(
+    name: &str,
+    templates: &serde_yaml::Mapping,
+    resolved: &mut HashMap<String, Value>,
+    chain: &mut Vec<String>,
+) -> Result<Value, String> {
+    if let Some(template) = resolved.get(name) {
+        return Ok(template.clone());
+    }
+    if chain.iter().any(|template| template == name) {
+        return Err(format!(
+            "cyclic template extends: {} -> {name}",
+            chain.join(" -> ")
+        ));
+    }
+    let template = templates.get(name).cloned().unwrap_or_default();
+    let template = match template.get("extends").and_then(Value::as_str) {
+        Some(base) if !templates.contains_key(base) => {
+            return Err(format!(
+                "template '{name}' extends unknown template '{base}'"
+            ));
+        }
+        Some(base) => {
+            chain.push(name.to_string());
+            let base = resolve_template(base, templates, resolved, chain)?;
+            chain.pop();
+            merge_values(base, template)
+        }
+        None => template,
+    };
+    resolved.insert(name.to_string(), template.clone());
+    Ok(template)
+}
+
+/// Deep merge `over` into `base`: mappings are merged key by key, any other value of `over`
+/// (scalars, and sequences such as `rounds`) replaces the one of `base`
+pub fn merge_values(base: Value, over: Value) -> Value {
+    match (base, over) {
+        (Value::Mapping(mut base), Value::Mapping(over)) => {
+            for (key, value) in over {
+                let value = match base.remove(&key) {
+                    Some(base_value) => merge_values(base_value, value),
+                    None => value,
+                };
+                base.insert(key, value);
+            }
+            Value::Mapping(base)
+        }
+        (_, over) => over,
+    }
+}
+
+/// Replace the `${VAR}` references of the string values of the parsed config, see `expand_env_str`.
+/// The comments and keys are left as is, and a value can't change the structure of the config.
+/// Outside of `args`, a string made of a single reference takes the type of its value if it is
+/// a boolean or a number written as it would be printed, e.g. `interval: ${INTERVAL}`.
+/// The `args` are parsed by the adapters and keep the strings, e.g. for a digit-only hex key.
+/// Errors with the key path of the value, e.g. `rounds[0].template.txs`.
+pub fn expand_env(
+    config: &mut Value,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    expand_env_at(config, "", lookup)
+}
+
+fn expand_env_at(
+    value: &mut Value,
+    path: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    match value {
+        Value::String(raw) => {
+            let expanded = expand_env_str(raw, lookup).map_err(|e| format!("{path}: {e}"))?;
+            let single_reference = raw.starts_with("${") && raw.find('}') == Some(raw.len() - 1);
+            let in_args = path == "args" || path.starts_with("args.");
+            *value = match retyped(&expanded) {
+                Some(typed) if single_reference && !in_args => typed,
+                _ => Value::String(expanded),
+            };
+        }
+        Value::Sequence(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                expand_env_at(value, &format!("{path}[{i}]"), lookup)?;
+            }
+        }
+        Value::Mapping(values) => {
+            for (key, value) in values.iter_mut() {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    key => serde_yaml::to_string(key)
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string(),
+                };
+                let path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{path}.{key}")
+                };
+                expand_env_at(value, &path, lookup)?;
+            }
+        }
+        Value::Tagged(tagged) => expand_env_at(&mut tagged.value, path, lookup)?,
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+    Ok(())
+}
+
+/// The boolean or number of an expanded value, only if it prints back to the same text,
+/// so that e.g. `0x1a` or `1e10` stay strings
+fn retyped(expanded: &str) -> Option<Value> {
+    let typed = match expanded {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match expanded.parse::<u64>() {
+            Ok(n) => Value::from(n),
+            Err(_) => match expanded.parse::<i64>() {
+                Ok(n) => Value::from(n),
+                Err(_) => Value::from(expanded.parse::<f64>().ok()?),
+            },
+        },
+    };
+    let printed = match &typed {
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+    (printed == expanded).then_some(typed)
+}
+
+/// Replace the `${VAR}` references of the string with the value of `VAR` given by `lookup`,
+/// or with `default` for `${VAR:-default}` if `VAR` is undefined. `$${` is kept as a literal `${`.
+/// Errors on an undefined variable without default.
+fn expand_env_str(raw: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut expanded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or(format!(
+            "unterminated variable reference: {}",
+            &rest[start..]
+        ))?;
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if name.is_empty() {
+            return Err("empty variable reference".to_string());
+        }
+        match (lookup(name), default) {
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => return Err(format!("undefined environment variable {name}")),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Read the rpcs listed in the given file, either as a JSON array of strings or one per line.
+/// Blank lines and lines starting with `#` are ignored. Errors if the file lists no rpcs.
+pub fn read_rpcs_file(f: &str) -> Result<Vec<String>, TestrpcError> {
+    let content = std::fs::read_to_string(f)
+        .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), f.to_string()))?;
+    let rpcs = if content.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<String>>(&content)
+            .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), f.to_string()))?
+    } else {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    };
+    if rpcs.is_empty() {
+        return Err(TestrpcError::LoadConfigError(
+            "no rpcs listed".to_string(),
+            f.to_string(),
+        ));
+    }
+    Ok(rpcs)
+}
+
+/// Read the transactions of a tx file, one hex or base64 encoded transaction per line.
+/// Blank lines are ignored. Errors if a line can't be decoded or the file lists no transactions.
+pub fn read_tx_file(f: &str) -> Result<Vec<Vec<u8>>, TestrpcError> {
+    let content = std::fs::read_to_string(f)
+        .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), f.to_string()))?;
+    let txs = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            let hex_line = line.strip_prefix("0x").unwrap_or(line);
+            hex::decode(hex_line)
+                .or_else(|_| base64::engine::general_purpose::STANDARD.decode(line))
+                .map_err(|_| {
+                    TestrpcError::LoadConfigError(
+                        format!("line {}: neither hex nor base64", i + 1),
+                        f.to_string(),
+                    )
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if txs.is_empty() {
+        return Err(TestrpcError::LoadConfigError(
+            "no transactions listed".to_string(),
+            f.to_string(),
+        ));
+    }
+    Ok(txs)
+}
+
 pub fn parse_config_yaml(raw_cfg_yaml: &str) -> Result<Config, TestrpcError> {
-    let config: Config = serde_yaml::from_str(raw_cfg_yaml)
+    let mut config: Value = serde_yaml::from_str(raw_cfg_yaml)
+        .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), "".to_string()))?;
+    resolve_template_extends(&mut config)
+        .map_err(|e| TestrpcError::LoadConfigError(e, "".to_string()))?;
+    let config: Config = serde_yaml::from_value(config)
         .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), "".to_string()))?;
+    check_rounds(&config).map_err(|e| TestrpcError::LoadConfigError(e, "".to_string()))?;
     Ok(config)
 }
+
+/// Validate the config before running it: the interval is nonzero unless `back_to_back` is set,
+/// there are rounds, and each round resolves to a template and only uses known rpcs.
+pub fn validate(config: &Config) -> Result<(), TestrpcError> {
+    let err = |msg: String| TestrpcError::LoadConfigError(msg, String::new());
+    if config.interval == 0 && !config.back_to_back {
+        return Err(err(
+            "interval must be nonzero, set back_to_back to run the rounds back to back".to_string(),
+        ));
+    }
+    if config.rounds.is_empty() {
+        return Err(err("no rounds declared".to_string()));
+    }
+    if config.max_duration == Some(0) {
+        return Err(err("max_duration must be nonzero".to_string()));
+    }
+    if config.pipeline_depth == Some(0) {
+        return Err(err("pipeline_depth must be nonzero".to_string()));
+    }
+    if config.rpcs.is_some() && config.rpcs_file.is_some() {
+        return Err(err("rpcs and rpcs_file are mutually exclusive".to_string()));
+    }
+    if let Some(adaptive) = &config.adaptive {
+        if !(0.0..1.0).contains(&adaptive.failure_threshold) {
+            return Err(err(
+                "adaptive.failure_threshold must be in [0, 1)".to_string()
+            ));
+        }
+        if adaptive.backoff_factor <= 0.0 || adaptive.backoff_factor >= 1.0 {
+            return Err(err("adaptive.backoff_factor must be in (0, 1)".to_string()));
+        }
+    }
+    let num_of_rpcs = config
+        .rpcs
+        .as_ref()
+        .map(|rpcs| rpcs.len())
+        .or(config.num_of_nodes);
+    for (r, round) in config.rounds.iter().enumerate() {
+        if let Some(template_name) = &round.use_template {
+            if round.template.is_none() && !config.round_templates.contains_key(template_name) {
+                return Err(err(format!(
+                    "Round {r}: unknown template '{template_name}'"
+                )));
+            }
+        }
+        if config.round_template(round).is_none() {
+            return Err(err(format!("Round {r}: no template defined")));
+        }
+        if let Some(template) = config.round_template(round) {
+            if template.rate == Some(0) {
+                return Err(err(format!("Round {r}: rate must be nonzero")));
+            }
+            if template.rate.is_some() && template.latency.is_some() {
+                return Err(err(format!(
+                    "Round {r}: rate and latency are mutually exclusive"
+                )));
+            }
+            match template.arrival {
+                Some(ArrivalDist::Constant) | None => {}
+                Some(_) if template.rate.is_some() || template.latency.is_some() => {
+                    return Err(err(format!(
+                        "Round {r}: arrival is mutually exclusive with rate and latency"
+                    )));
+                }
+                Some(ArrivalDist::Poisson { lambda }) if !(lambda.is_finite() && lambda > 0.0) => {
+                    return Err(err(format!(
+                        "Round {r}: arrival lambda must be positive, got {lambda}"
+                    )));
+                }
+                Some(ArrivalDist::Uniform { min, max }) if min > max => {
+                    return Err(err(format!("Round {r}: arrival min must not exceed max")));
+                }
+                Some(_) => {}
+            }
+            if template
+                .tx_size_max
+                .is_some_and(|max| max < template.tx_size)
+            {
+                return Err(err(format!(
+                    "Round {r}: tx_size_max must be at least tx_size"
+                )));
+            }
+            if let Some(TxSource::File { path }) = &template.tx_source {
+                read_tx_file(path).map_err(|e| err(format!("Round {r}: {e}")))?;
+            }
+        }
+        if round.rpcs.is_empty() && !round.to_all {
+            return Err(err(format!("Round {r}: no rpcs selected")));
+        }
+        if let Some(num_of_rpcs) = num_of_rpcs {
+            if let Some(rpc) = round.rpcs.iter().find(|rpc| **rpc >= num_of_rpcs) {
+                return Err(err(format!(
+                    "Round {r}: rpc index {rpc} out of bounds, there are {num_of_rpcs} rpcs"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Structural checks of the rounds, done when the config is loaded
+fn check_rounds(config: &Config) -> Result<(), String> {
+    for (r, round) in config.rounds.iter().enumerate() {
+        if round.to_all && !round.rpcs.is_empty() {
+            return Err(format!("Round {r}: rpcs and to_all are mutually exclusive"));
+        }
+        if let Some(sample) = round.sample {
+            if !(sample > 0.0 && sample <= 1.0) {
+                return Err(format!("Round {r}: sample must be in (0, 1], got {sample}"));
+            }
+        }
+        if round.broadcast_identical && round.weights.is_some() {
+            return Err(format!(
+                "Round {r}: broadcast_identical sends the same txs to every rpc, weights can't be set"
+            ));
+        }
+        if round.to_all && round.weights.is_some() {
+            return Err(format!("Round {r}: weights require explicit rpcs"));
+        }
+        if let Some(weights) = &round.weights {
+            if weights.len() != round.rpcs.len() {
+                return Err(format!(
+                    "Round {r}: expected {} weights (one per rpc), got {}",
+                    round.rpcs.len(),
+                    weights.len()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assert that the config `base` with `from` replaced with `to` fails to load or to validate,
+    /// with an error containing `expected`
+    fn assert_config_error(base: &str, from: &str, to: &str, expected: &str) {
+        match parse_config_yaml(&base.replace(from, to)).and_then(|cfg| validate(&cfg)) {
+            Err(TestrpcError::LoadConfigError(msg, _)) => assert!(msg.contains(expected), "{msg}"),
+            res => panic!("Expected a config error for {to}, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_latency() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates:
+  slow:
+    txs: 10
+    tx_size: 100
+    latency: 50ms
+rounds:
+  - rpcs: [0]
+    use_template: slow
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        let template = cfg.rounds[0].get_template(cfg.round_templates).unwrap();
+        assert_eq!(template.latency, Some(Duration::from_millis(50)));
+
+        let err = parse_config_yaml(&raw_cfg_yaml.replace("50ms", "fast")).unwrap_err();
+        assert!(matches!(err, TestrpcError::LoadConfigError(_, _)));
+    }
+
+    #[test]
+    fn test_validate() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates:
+  small:
+    txs: 1
+    tx_size: 1
+rpcs:
+  - http://localhost:5000
+  - http://localhost:5001
+rounds:
+  - rpcs: [0, 1]
+    use_template: small
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert!(validate(&cfg).is_ok());
+
+        // the rounds run back to back
+        let back_to_back = parse_config_yaml(
+            &raw_cfg_yaml.replace("interval: 1", "interval: 0\nback_to_back: true"),
+        );
+        assert!(validate(&back_to_back.unwrap()).is_ok());
+
+        let cases = [
+            ("interval: 1", "interval: 0", "interval must be nonzero"),
+            (
+                "use_template: small",
+                "use_template: big",
+                "unknown template 'big'",
+            ),
+            ("rpcs: [0, 1]", "rpcs: [0, 2]", "rpc index 2 out of bounds"),
+            ("rpcs: [0, 1]", "rpcs: []", "no rpcs selected"),
+            (
+                "tx_size: 1",
+                "tx_size: 1\n    rate: 0",
+                "rate must be nonzero",
+            ),
+            (
+                "tx_size: 1",
+                "tx_size: 1\n    rate: 10\n    latency: 1ms",
+                "mutually exclusive",
+            ),
+            (
+                "tx_size: 1",
+                "tx_size: 2\n    tx_size_max: 1",
+                "tx_size_max must be at least tx_size",
+            ),
+            (
+                "interval: 1",
+                "interval: 1\nrpcs_file: rpcs.txt",
+                "rpcs and rpcs_file are mutually exclusive",
+            ),
+            (
+                "interval: 1",
+                "interval: 1\npipeline_depth: 0",
+                "pipeline_depth must be nonzero",
+            ),
+        ];
+        for (from, to, expected) in cases {
+            assert_config_error(raw_cfg_yaml, from, to, expected);
+        }
+
+        let mut cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        cfg.rounds.clear();
+        assert!(validate(&cfg).is_err());
+    }
+
+    #[test]
+    fn test_to_all() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rounds:
+  - to_all: true
+    template:
+      txs: 1
+      tx_size: 1
+  - rpcs: [1]
+    template:
+      txs: 1
+      tx_size: 1
+"#;
+        let mut cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert!(validate(&cfg).is_ok());
+        cfg.expand_to_all(3);
+        assert_eq!(cfg.rounds[0].rpcs, vec![0, 1, 2]);
+        assert_eq!(cfg.rounds[1].rpcs, vec![1]);
+        // expanding again follows the new number of rpcs
+        cfg.expand_to_all(2);
+        assert_eq!(cfg.rounds[0].rpcs, vec![0, 1]);
+
+        let cases = [
+            (
+                "to_all: true",
+                "to_all: true\n    rpcs: [0]",
+                "mutually exclusive",
+            ),
+            ("to_all: true", "to_all: true\n    weights: [1]", "weights"),
+        ];
+        for (from, to, expected) in cases {
+            assert_config_error(raw_cfg_yaml, from, to, expected);
+        }
+    }
+
+    #[test]
+    fn test_broadcast_identical() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rounds:
+  - rpcs: [0, 1]
+    broadcast_identical: true
+    template:
+      txs: 2
+      tx_size: 4
+      tx_size_max: 8
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert!(cfg.rounds[0].broadcast_identical);
+        let err = parse_config_yaml(&raw_cfg_yaml.replace(
+            "broadcast_identical: true",
+            "broadcast_identical: true\n    weights: [1, 2]",
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("weights"), "{err}");
+
+        // the requests of a broadcast draw the same tx sizes
+        let template = RoundTemplate {
+            broadcast_seed: Some(1),
+            ..cfg.rounds[0].template.clone().unwrap()
+        };
+        assert_eq!(template.tx_sizes(1, 1), template.tx_sizes(1, 2));
+        assert_eq!(
+            template.payload_rng(None, 1, 1).unwrap().random::<u64>(),
+            template.payload_rng(Some(3), 1, 2).unwrap().random::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_arrival() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates:
+  poisson:
+    txs: 1001
+    tx_size: 1
+    arrival:
+      type: poisson
+      lambda: 100
+  uniform:
+    txs: 11
+    tx_size: 1
+    arrival:
+      type: uniform
+      min: 1ms
+      max: 3ms
+rounds:
+  - rpcs: [0]
+    use_template: poisson
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert!(validate(&cfg).is_ok());
+        let delays = cfg.round_templates["poisson"].arrival_delays(1, 1).unwrap();
+        assert_eq!(delays.len(), 1000);
+        // the mean delay of a Poisson process of 100 tx/s is 10ms
+        let mean = delays.iter().sum::<Duration>() / 1000;
+        assert!(
+            (Duration::from_millis(8)..Duration::from_millis(12)).contains(&mean),
+            "{mean:?}"
+        );
+        let delays = cfg.round_templates["uniform"].arrival_delays(1, 1).unwrap();
+        assert!(delays
+            .iter()
+            .all(|delay| (Duration::from_millis(1)..=Duration::from_millis(3)).contains(delay)));
+
+        // constant by default, the txs are sent one by one only with a latency
+        let template = RoundTemplate {
+            txs: 3,
+            tx_size: 1,
+            ..Default::default()
+        };
+        assert_eq!(template.arrival_delays(1, 1), None);
+        let template = RoundTemplate {
+            latency: Some(Duration::from_millis(5)),
+            ..template
+        };
+        assert_eq!(
+            template.arrival_delays(1, 1),
+            Some(vec![Duration::from_millis(5); 2])
+        );
+        // the delays of a tiny lambda are capped
+        let template = RoundTemplate {
+            arrival: Some(ArrivalDist::Poisson { lambda: 1e-300 }),
+            ..template
+        };
+        assert!(template
+            .arrival_delays(1, 1)
+            .unwrap()
+            .iter()
+            .all(|delay| *delay == MAX_ARRIVAL_DELAY));
+
+        let cases = [
+            ("lambda: 100", "lambda: 0", "lambda must be positive"),
+            (
+                "lambda: 100",
+                "lambda: 100\n    rate: 10",
+                "mutually exclusive",
+            ),
+        ];
+        for (from, to, expected) in cases {
+            assert_config_error(raw_cfg_yaml, from, to, expected);
+        }
+    }
+
+    #[test]
+    fn test_tx_sizes() {
+        let mut template = RoundTemplate {
+            txs: 100,
+            tx_size: 10,
+            ..Default::default()
+        };
+        assert_eq!(template.tx_sizes(1, 1), vec![10; 100]);
+        template.tx_size_max = Some(20);
+        let sizes = template.tx_sizes(1, 1);
+        assert_eq!(sizes.len(), 100);
+        assert!(sizes.iter().all(|size| (10..=20).contains(size)));
+        assert!(sizes.iter().any(|size| *size != sizes[0]));
+        assert_eq!(template.mean_tx_size(), 15);
+    }
+
+    #[test]
+    fn test_template_extends() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates:
+  base:
+    txs: 10
+    tx_size: 100
+    latency: 50ms
+  big_txs:
+    extends: base
+    tx_size: 1000
+  bigger_txs:
+    extends: big_txs
+    txs: 20
+rounds:
+  - rpcs: [0]
+    use_template: bigger_txs
+  - rpcs: [0]
+    template:
+      extends: base
+      rate: 10
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        let big = &cfg.round_templates["big_txs"];
+        assert_eq!((big.txs, big.tx_size), (10, 1000));
+        let bigger = cfg.round_template(&cfg.rounds[0]).unwrap();
+        assert_eq!((bigger.txs, bigger.tx_size), (20, 1000));
+        assert_eq!(bigger.latency, Some(Duration::from_millis(50)));
+        let inline = cfg.round_template(&cfg.rounds[1]).unwrap();
+        assert_eq!((inline.txs, inline.rate), (10, Some(10)));
+
+        let cases = [
+            (
+                "extends: big_txs",
+                "extends: small",
+                "unknown template 'small'",
+            ),
+            (
+                "    latency: 50ms",
+                "    extends: bigger_txs",
+                "cyclic template extends",
+            ),
+            (
+                "extends: base\n      rate",
+                "extends: none\n      rate",
+                "Round 1",
+            ),
+        ];
+        for (from, to, expected) in cases {
+            assert_config_error(raw_cfg_yaml, from, to, expected);
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rounds:
+  - rpcs: [0, 1, 2, 3, 4, 5, 6, 7]
+    weights: [0, 1, 2, 3, 4, 5, 6, 7]
+    sample: 0.25
+    template:
+      txs: 1
+      tx_size: 1
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        let round = cfg.rounds[0].sampled(1);
+        assert_eq!(round.rpcs.len(), 2);
+        // the weights follow their rpcs
+        for (i, rpc) in round.rpcs.iter().enumerate() {
+            assert_eq!(round.weight(i), *rpc as u32);
+        }
+        // without a run seed, each call draws other rpcs
+        assert!((1..=13).contains(&cfg.planned_txs(1)));
+
+        for sample in ["0", "1.5", "-0.1", ".nan"] {
+            assert_config_error(raw_cfg_yaml, "0.25", sample, "sample");
+        }
+    }
+
+    #[test]
+    fn test_retain_rpcs() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+  - http://localhost:5001
+  - http://localhost:5002
+rounds:
+  - rpcs: [0, 1, 2]
+    weights: [1, 2, 3]
+    template:
+      txs: 1
+      tx_size: 1
+  - rpcs: [1]
+    template:
+      txs: 1
+      tx_size: 1
+"#;
+        let mut cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert_eq!(cfg.retain_rpcs(&[true, false, true]), 1);
+        assert_eq!(
+            cfg.rpcs.unwrap(),
+            vec!["http://localhost:5000", "http://localhost:5002"]
+        );
+        assert_eq!(cfg.rounds.len(), 1);
+        assert_eq!(cfg.rounds[0].rpcs, vec![0, 1]);
+        assert_eq!(cfg.rounds[0].weights, Some(vec![1, 3]));
+    }
+
+    #[test]
+    fn test_truncate_rpcs() {
+        let mut cfg = parse_config_yaml(
+            r#"
+interval: 1
+num_of_nodes: 4
+adapter: hotshot
+args: {}
+round_templates: {}
+rounds:
+  - rpcs: [0, 3]
+  - rpcs: [2, 3]
+"#,
+        )
+        .unwrap();
+        assert!(cfg.strict_node_count);
+        assert_eq!(cfg.truncate_rpcs(2), 1);
+        assert_eq!(cfg.rounds.len(), 1);
+        assert_eq!(cfg.rounds[0].rpcs, vec![0]);
+    }
+
+    #[test]
+    fn test_default_template() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert!(validate(&cfg).is_ok());
+        let template = cfg.round_template(&cfg.rounds[0]).unwrap();
+        assert_eq!((template.txs, template.tx_size), (1, 100));
+
+        let cfg = parse_config_yaml(&format!(
+            "{raw_cfg_yaml}default_template:\n  txs: 5\n  tx_size: 10\n"
+        ))
+        .unwrap();
+        let template = cfg.round_template(&cfg.rounds[0]).unwrap();
+        assert_eq!((template.txs, template.tx_size), (5, 10));
+    }
+
+    #[test]
+    fn test_extends() {
+        let dir = std::env::temp_dir().join(format!("testrpc-extends-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("base")).unwrap();
+        std::fs::write(
+            dir.join("base/base.yaml"),
+            r#"
+interval: 1
+iterations: 3
+adapter: hotshot
+args:
+  coordinator_url: http://localhost:3030
+  batch: true
+round_templates: {}
+rounds:
+  - rpcs: [0, 1]
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("testbed.yaml"),
+            r#"
+extends: base/base.yaml
+iterations: 5
+args:
+  batch: false
+rounds:
+  - rpcs: [2]
+"#,
+        )
+        .unwrap();
+        let cfg = load_config(&dir.join("testbed.yaml").to_string_lossy()).unwrap();
+        assert_eq!(cfg.interval, 1);
+        assert_eq!(cfg.iterations, Some(5));
+        assert_eq!(cfg.args["coordinator_url"], "http://localhost:3030");
+        assert_eq!(cfg.args["batch"], false);
+        assert_eq!(cfg.rounds.len(), 1);
+        assert_eq!(cfg.rounds[0].rpcs, vec![2]);
+
+        std::fs::write(dir.join("a.yaml"), "extends: b.yaml\n").unwrap();
+        std::fs::write(dir.join("b.yaml"), "extends: a.yaml\n").unwrap();
+        let err = load_config(&dir.join("a.yaml").to_string_lossy()).unwrap_err();
+        assert!(err.to_string().contains("cyclic extends"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_formats() {
+        let dir = std::env::temp_dir().join(format!("testrpc-formats-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("base.toml"),
+            r#"
+interval = 1
+iterations = 3
+adapter = "hotshot"
+
+[args]
+coordinator_url = "http://localhost:3030"
+
+[round_templates.10_txs]
+txs = 10
+tx_size = 100
+
+[[rounds]]
+rpcs = [0, 1]
+use_template = "10_txs"
+"#,
+        )
+        .unwrap();
+        let cfg = load_config(&dir.join("base.toml").to_string_lossy()).unwrap();
+        assert_eq!(cfg.iterations, Some(3));
+        assert_eq!(cfg.args["coordinator_url"], "http://localhost:3030");
+        assert_eq!(cfg.round_templates["10_txs"].txs, 10);
+        assert_eq!(cfg.rounds[0].rpcs, vec![0, 1]);
+
+        // a base of another format can be extended
+        std::fs::write(
+            dir.join("testbed.json"),
+            r#"{"extends": "base.toml", "iterations": 5, "rounds": [{"rpcs": [2], "use_template": "10_txs"}]}"#,
+        )
+        .unwrap();
+        let cfg = load_config(&dir.join("testbed.json").to_string_lossy()).unwrap();
+        assert_eq!((cfg.interval, cfg.iterations), (1, Some(5)));
+        assert_eq!(cfg.rounds[0].rpcs, vec![2]);
+
+        std::fs::write(dir.join("invalid.toml"), "interval: 1\n").unwrap();
+        assert!(load_config(&dir.join("invalid.toml").to_string_lossy()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_env_str() {
+        let lookup = |name: &str| (name == "URL").then(|| "http://localhost:3030".to_string());
+        assert_eq!(
+            expand_env_str("${URL}/nodes", lookup).unwrap(),
+            "http://localhost:3030/nodes"
+        );
+        assert_eq!(
+            expand_env_str("${MISSING:-1}, ${URL:-x}", lookup).unwrap(),
+            "1, http://localhost:3030"
+        );
+        assert_eq!(expand_env_str("$${URL}", lookup).unwrap(), "${URL}");
+        assert!(expand_env_str("${MISSING}", lookup)
+            .unwrap_err()
+            .contains("MISSING"));
+        assert!(expand_env_str("${URL", lookup).is_err());
+    }
+
+    #[test]
+    fn test_expand_env() {
+        let lookup = |name: &str| match name {
+            "INTERVAL" => Some("5".to_string()),
+            "KEY" => Some("a: b # c\nd".to_string()),
+            "HEX_KEY" => {
+                Some("4646464646464646464646464646464646464646464646464646464646464646".to_string())
+            }
+            "TAG" => Some("0x1a".to_string()),
+            "LARGE" => Some("1e10".to_string()),
+            "RATIO" => Some("0.5".to_string()),
+            _ => None,
+        };
+        let expand = |raw: &str| {
+            let mut config = serde_yaml::from_str(raw).unwrap();
+            expand_env(&mut config, &lookup).map(|()| config)
+        };
+        let config = expand(
+            "# set ${MISSING} to ...\ninterval: ${INTERVAL}\nargs:\n  key: ${KEY}\n  id: id-${INTERVAL}\n",
+        )
+        .unwrap();
+        // the comment isn't expanded, the value doesn't change the structure
+        assert_eq!(config["interval"], Value::from(5));
+        assert_eq!(config["args"]["key"], Value::from("a: b # c\nd"));
+        assert_eq!(config["args"]["id"], Value::from("id-5"));
+
+        // the args keep the strings, the other values are only typed if they print back the same
+        let config = expand(
+            "args:\n  private_key: ${HEX_KEY}\n  tx_prefix: \"${INTERVAL}\"\na: ${TAG}\nb: ${LARGE}\nc: ${RATIO}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config["args"]["private_key"],
+            Value::from("4646464646464646464646464646464646464646464646464646464646464646")
+        );
+        assert_eq!(config["args"]["tx_prefix"], Value::from("5"));
+        assert_eq!(config["a"], Value::from("0x1a"));
+        assert_eq!(config["b"], Value::from("1e10"));
+        assert_eq!(config["c"], Value::from(0.5));
+
+        let err = expand("rounds:\n  - template:\n      txs: ${MISSING}\n").unwrap_err();
+        assert!(err.starts_with("rounds[0].template.txs: "), "{err}");
+        assert!(expand("a: ${}").unwrap_err().starts_with("a: "));
+    }
+
+    #[test]
+    fn test_read_rpcs_file() {
+        let dir = std::env::temp_dir().join(format!("testrpc-rpcs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        std::fs::write(
+            path("rpcs.txt"),
+            "# nodes\nhttp://localhost:5000\n\n  http://localhost:5001\n",
+        )
+        .unwrap();
+        std::fs::write(path("rpcs.json"), r#"["http://localhost:5000"]"#).unwrap();
+        std::fs::write(path("empty.txt"), "\n# no nodes\n").unwrap();
+
+        assert_eq!(
+            read_rpcs_file(&path("rpcs.txt")).unwrap(),
+            vec!["http://localhost:5000", "http://localhost:5001"]
+        );
+        assert_eq!(
+            read_rpcs_file(&path("rpcs.json")).unwrap(),
+            vec!["http://localhost:5000"]
+        );
+        assert!(read_rpcs_file(&path("empty.txt")).is_err());
+        assert!(read_rpcs_file(&path("missing.txt")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_tx_file() {
+        let dir = std::env::temp_dir().join(format!("testrpc-txs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        // hex, 0x prefixed hex and base64 of [1, 2, 3]
+        std::fs::write(path("txs.txt"), "010203\n\n0x010203\nAQID\n").unwrap();
+        std::fs::write(path("bad.txt"), "010203\nnot a tx!\n").unwrap();
+        std::fs::write(path("empty.txt"), "\n").unwrap();
+
+        assert_eq!(
+            read_tx_file(&path("txs.txt")).unwrap(),
+            vec![vec![1, 2, 3]; 3]
+        );
+        assert!(matches!(
+            read_tx_file(&path("bad.txt")),
+            Err(TestrpcError::LoadConfigError(e, _)) if e.contains("line 2")
+        ));
+        assert!(read_tx_file(&path("empty.txt")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_next_interval() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rounds: []
+"#;
+        let mut cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert_eq!(cfg.next_interval(), Duration::from_secs(1));
+        cfg.interval_jitter = Some(0);
+        assert_eq!(cfg.next_interval(), Duration::from_secs(1));
+        cfg.interval_jitter = Some(50);
+        for _ in 0..10 {
+            let interval = cfg.next_interval();
+            assert!(interval >= Duration::from_secs(1));
+            assert!(interval <= Duration::from_millis(1050));
+        }
+    }
+
+    #[test]
+    fn test_planned_txs() {
+        let raw_cfg_yaml = r#"
+interval: 1
+iterations: 3
+adapter: hotshot
+args: {}
+round_templates: {}
+rounds:
+  - rpcs: [0, 1]
+    template:
+      txs: 10
+      tx_size: 1
+  - rpcs: [1]
+    weights: [3]
+    template:
+      txs: 1
+      tx_size: 1
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert_eq!(cfg.planned_txs(1), 20);
+        assert_eq!(cfg.planned_txs(2), 3);
+        assert_eq!(cfg.planned_txs(3), 20);
+        assert_eq!(cfg.total_planned_txs(), Some(43));
+    }
+
+    #[test]
+    fn test_adaptive_load() {
+        let adaptive = AdaptiveConfig {
+            failure_threshold: 0.1,
+            backoff_factor: 0.5,
+        };
+        assert_eq!(adaptive.next_load(1.0, 0.5), 0.5);
+        assert_eq!(adaptive.next_load(0.5, 0.2), 0.25);
+        // ramps back up when healthy, up to the configured txs
+        assert_eq!(adaptive.next_load(0.25, 0.1), 0.5);
+        assert_eq!(adaptive.next_load(0.75, 0.0), 1.0);
+        assert_eq!(adaptive.txs(10, 0.25), 3);
+        assert_eq!(adaptive.txs(10, 0.0001), 1);
+    }
+
+    #[test]
+    fn test_ramp_txs() {
+        let ramp = RampConfig {
+            start_txs: 10,
+            step: 5,
+            max_txs: 22,
+        };
+        assert_eq!(ramp.txs(1), 10);
+        assert_eq!(ramp.txs(2), 15);
+        assert_eq!(ramp.txs(3), 20);
+        assert_eq!(ramp.txs(4), 22);
+    }
+
+    #[test]
+    fn test_weights_len() {
+        let raw_cfg_yaml = r#"
+interval: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rounds:
+  - rpcs: [0, 1]
+    weights: [1, 2]
+    template:
+      txs: 1
+      tx_size: 1
+"#;
+        let cfg = parse_config_yaml(raw_cfg_yaml).unwrap();
+        assert_eq!(cfg.rounds[0].weight(1), 2);
+        let err = parse_config_yaml(&raw_cfg_yaml.replace("[1, 2]", "[1]")).unwrap_err();
+        assert!(matches!(err, TestrpcError::LoadConfigError(_, _)));
+    }
+}
@@ -1,7 +1,40 @@
-use tokio::sync::broadcast;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// Reason for stopping a context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// An exit signal was received
+    Signal,
+    /// The configured number of iterations was reached
+    MaxIterations,
+    /// The configured max duration elapsed
+    Deadline,
+    /// A fatal error occurred
+    Error(String),
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Signal => write!(f, "signal"),
+            StopReason::MaxIterations => write!(f, "max iterations"),
+            StopReason::Deadline => write!(f, "deadline"),
+            StopReason::Error(e) => write!(f, "error: {e}"),
+        }
+    }
+}
 
 pub struct Context {
-    pub tx: broadcast::Sender<()>,
+    /// Reason of the first stop, None until the context is stopped.
+    /// A watch channel rather than a broadcast one, so that receivers subscribing
+    /// after the stop still observe it.
+    tx: watch::Sender<Option<StopReason>>,
+    /// Cancelled on the first stop, for the sends in progress to exit early
+    cancel: CancellationToken,
 }
 
 unsafe impl Send for Context {}
@@ -11,19 +44,80 @@ impl Context {
         Self::default()
     }
 
-    pub fn stop(&self) {
-        let _ = self.tx.send(());
+    /// Stop the context with the given reason, only the first reason is kept
+    pub fn stop(&self, reason: StopReason) {
+        self.tx.send_if_modified(|stopped| {
+            let first = stopped.is_none();
+            if first {
+                *stopped = Some(reason);
+            }
+            first
+        });
+        self.cancel.cancel();
+    }
+
+    /// Receiver of the stop of the context, whenever it subscribed
+    pub fn recv(&self) -> StopReceiver {
+        StopReceiver(self.tx.subscribe())
+    }
+
+    /// New context, stopped with the same reason when this one is stopped.
+    /// Stopping the child doesn't stop this context.
+    pub fn child(&self) -> Arc<Self> {
+        let child = Arc::new(Self::new());
+        if let Some(reason) = self.stop_reason() {
+            child.stop(reason);
+            return child;
+        }
+        let mut recv = self.recv();
+        let weak = Arc::downgrade(&child);
+        tokio::spawn(async move {
+            let reason = recv.recv().await;
+            if let Some(child) = weak.upgrade() {
+                child.stop(reason);
+            }
+        });
+        child
+    }
+
+    /// Token cancelled when the context is stopped
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
     }
 
-    pub fn recv(&self) -> broadcast::Receiver<()> {
-        self.tx.subscribe()
+    /// Reason the context was stopped with, None if it wasn't stopped
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.tx.borrow().clone()
+    }
+}
+
+/// Receiver of the stop of a context
+pub struct StopReceiver(watch::Receiver<Option<StopReason>>);
+
+impl StopReceiver {
+    /// Wait until the context is stopped and return the reason of its first stop,
+    /// right away if it is already stopped. Never returns if the context is dropped unstopped.
+    pub async fn recv(&mut self) -> StopReason {
+        // the borrow of the value is released before waiting forever
+        let stopped = self
+            .0
+            .wait_for(Option::is_some)
+            .await
+            .map(|reason| reason.clone());
+        match stopped {
+            Ok(reason) => reason.unwrap_or(StopReason::Signal),
+            Err(_) => std::future::pending().await,
+        }
     }
 }
 
 impl Default for Context {
     fn default() -> Self {
-        let (tx, _) = broadcast::channel(1);
-        Self { tx }
+        let (tx, _) = watch::channel(None);
+        Self {
+            tx,
+            cancel: CancellationToken::new(),
+        }
     }
 }
 
@@ -36,12 +130,46 @@ mod test {
     async fn test_context() {
         let ctx = Context::new();
         let mut recv = ctx.recv();
-        let mut recv2 = ctx.recv();
+        let cancel = ctx.cancellation_token();
+        let ctx = std::sync::Arc::new(ctx);
+        let ctx_cloned = ctx.clone();
         tokio::spawn(async move {
             sleep(Duration::from_millis(10)).await;
-            ctx.stop();
-            assert!(recv2.recv().await.is_ok());
+            ctx_cloned.stop(StopReason::Signal);
         });
-        assert!(recv.recv().await.is_ok());
+        assert_eq!(recv.recv().await, StopReason::Signal);
+        assert!(cancel.is_cancelled());
+        // the first reason is kept
+        ctx.stop(StopReason::MaxIterations);
+        assert_eq!(ctx.stop_reason(), Some(StopReason::Signal));
+    }
+
+    #[tokio::test]
+    async fn test_recv_after_stop() {
+        let ctx = Context::new();
+        let mut early = ctx.recv();
+        ctx.stop(StopReason::Deadline);
+        ctx.stop(StopReason::Signal);
+        // subscribed after the stop, the receiver still observes it, with the first reason
+        let mut late = ctx.recv();
+        assert_eq!(late.recv().await, StopReason::Deadline);
+        assert_eq!(early.recv().await, StopReason::Deadline);
+        // and keeps observing it
+        assert_eq!(early.recv().await, StopReason::Deadline);
+    }
+
+    #[tokio::test]
+    async fn test_child() {
+        let ctx = Context::new();
+        let child = ctx.child();
+        child.stop(StopReason::MaxIterations);
+        assert_eq!(ctx.stop_reason(), None);
+
+        let child = ctx.child();
+        let mut recv = child.recv();
+        ctx.stop(StopReason::Signal);
+        assert_eq!(recv.recv().await, StopReason::Signal);
+        // a child of a stopped context starts stopped
+        assert_eq!(ctx.child().stop_reason(), Some(StopReason::Signal));
     }
 }
@@ -0,0 +1,75 @@
+/// Liveness and readiness probes of a running testrpc process, for orchestrators like Kubernetes
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    common::{probe, TestrpcError},
+    ctx,
+};
+
+/// Serve `/healthz`, always answering 200 while the process runs, and `/readyz`, answering 200
+/// once `ready` is set (503 before) on the given address, until the context is stopped
+pub async fn serve(
+    ctx: Arc<ctx::Context>,
+    addr: &str,
+    ready: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>, TestrpcError> {
+    let handle = probe::serve(ctx, addr, "health", move |path| {
+        response(path, ready.load(Ordering::SeqCst))
+    })
+    .await?;
+    tracing::info!(
+        "Serving health checks at http://{}/healthz and /readyz",
+        addr
+    );
+    Ok(handle)
+}
+
+/// Response to the request of the path, given the readiness of the process
+fn response(path: &str, ready: bool) -> Option<String> {
+    match path {
+        "/healthz" => Some(probe::response("200 OK", None, "ok")),
+        "/readyz" if ready => Some(probe::response("200 OK", None, "ok")),
+        "/readyz" => Some(probe::response(
+            "503 Service Unavailable",
+            None,
+            "not ready",
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response() {
+        let status = |path, ready| {
+            response(path, ready).map(|response| response.lines().next().unwrap().to_string())
+        };
+        assert_eq!(status("/healthz", false).unwrap(), "HTTP/1.1 200 OK");
+        assert_eq!(
+            status("/readyz", false).unwrap(),
+            "HTTP/1.1 503 Service Unavailable"
+        );
+        assert_eq!(status("/readyz", true).unwrap(), "HTTP/1.1 200 OK");
+        assert_eq!(status("/metrics", true), None);
+        assert!(response("/readyz", false)
+            .unwrap()
+            .ends_with("Content-Length: 9\r\nConnection: close\r\n\r\nnot ready"));
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let ctx = Arc::new(ctx::Context::new());
+        let handle = serve(ctx.clone(), "127.0.0.1:0", Arc::default())
+            .await
+            .unwrap();
+        ctx.stop(ctx::StopReason::Signal);
+        handle.await.unwrap();
+    }
+}
@@ -1,10 +1,158 @@
-use std::env;
+mod ws;
 
+use futures::future::join_all;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::common::TestrpcError;
 
+/// Default timeout of a request
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long idle connections are kept in the pool
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Interval of TCP keep-alive probes on pooled connections
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Transport options of a client
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// Use HTTP/2 with prior knowledge, multiplexing the requests over one connection per node.
+    /// Servers that only speak HTTP/1.1 reject the connection, so every request to them fails.
+    /// Over https, HTTP/2 is negotiated anyway and HTTP/1.1 stays a fallback.
+    pub http2: bool,
+    /// Path to a PEM root certificate to trust, for endpoints behind TLS with a custom CA
+    pub ca_cert: Option<String>,
+    /// Headers attached to every request (e.g. Authorization), their values are never logged
+    pub headers: HashMap<String, String>,
+    /// Path of the JSON-RPC endpoint (e.g. `/rpc`), appended to the urls without a path
+    pub rpc_path: Option<String>,
+    /// Answer the requests without sending them, None for a real run
+    pub dry_run: Option<DryRun>,
+}
+
+impl ClientOptions {
+    /// Transport options of the adapter args `http2`, `ca_cert`, `headers` and `rpc_path`,
+    /// the dry run being left to the adapter
+    pub fn from_args(args: &HashMap<String, serde_yaml::Value>) -> Result<Self, TestrpcError> {
+        use serde_yaml::Value;
+
+        let http2 = matches!(args.get("http2"), Some(Value::Bool(true)));
+        let ca_cert = match args.get("ca_cert") {
+            Some(Value::String(ca_cert)) => Some(ca_cert.clone()),
+            _ => None,
+        };
+        let headers = match args.get("headers") {
+            Some(Value::Mapping(headers)) => headers
+                .iter()
+                .map(|(name, value)| match (name, value) {
+                    (Value::String(name), Value::String(value)) => {
+                        Ok((name.clone(), value.clone()))
+                    }
+                    _ => Err(TestrpcError::MissingArgs(
+                        "headers must map strings to strings".to_string(),
+                    )),
+                })
+                .collect::<Result<HashMap<_, _>, _>>()?,
+            _ => HashMap::new(),
+        };
+        let rpc_path = match args.get("rpc_path") {
+            Some(Value::String(path)) => Some(path.clone()),
+            _ => None,
+        };
+        Ok(ClientOptions {
+            http2,
+            ca_cert,
+            headers,
+            rpc_path,
+            dry_run: None,
+        })
+    }
+}
+
+/// JSON-RPC client, connections are pooled and kept alive across requests.
+/// Requests to `ws://` and `wss://` urls are sent over one WebSocket connection per endpoint.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    /// Headers of the WebSocket handshakes
+    headers: HeaderMap,
+    /// Open WebSocket connections, by url
+    ws: Arc<Mutex<HashMap<String, WsSlot>>>,
+    /// See `ClientOptions::rpc_path`
+    rpc_path: Option<String>,
+    /// See `ClientOptions::dry_run`
+    dry_run: Option<DryRun>,
+}
+
+/// Client shared by the free functions of this module
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Returns the shared client, building it on first use. It always sends the requests,
+/// a dry run needs a `Client` built with `ClientOptions::dry_run`.
+fn client() -> Result<&'static Client, TestrpcError> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+    let client = Client::new(&ClientOptions::default())?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+/// Prepends `http://` to the URL unless it already carries a scheme
+pub fn with_scheme(url: &str) -> String {
+    if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{url}")
+    }
+}
+
+/// Appends the path to the URL (with a scheme) unless it already has an explicit path
+pub fn with_path(url: &str, path: Option<&str>) -> String {
+    let url = with_scheme(url);
+    let Some(path) = path.map(|path| path.trim_start_matches('/')) else {
+        return url;
+    };
+    // an invalid url is left as is, its requests fail with the error of the client
+    match reqwest::Url::parse(&url) {
+        Ok(mut parsed) if parsed.path() == "/" => {
+            // the query, if any, stays after the path
+            parsed.set_path(path);
+            parsed.into()
+        }
+        _ => url,
+    }
+}
+
+/// Builds the header map of the given headers, marking the values as sensitive
+fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap, TestrpcError> {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        let invalid = |e: String| {
+            TestrpcError::LoadConfigError(format!("Invalid header {name}: {e}"), String::new())
+        };
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+        let mut value = HeaderValue::from_str(value).map_err(|e| invalid(e.to_string()))?;
+        value.set_sensitive(true);
+        header_map.insert(name, value);
+    }
+    Ok(header_map)
+}
+
+/// Maps a request error, reporting elapsed timeouts explicitly
+fn request_error(e: reqwest::Error, timeout: Duration, context: &str) -> TestrpcError {
+    if e.is_timeout() {
+        return TestrpcError::RequestError(format!("Request timed out after {timeout:?}"));
+    }
+    TestrpcError::RequestError(format!("{context}: {e}"))
+}
+
 /// RPC request structure
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct RpcRequest {
@@ -18,80 +166,595 @@ pub struct RpcRequest {
     id: u64,
 }
 
+impl RpcRequest {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+            id,
+        }
+    }
+}
+
 /// RPC response structure
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct RpcResponse {
     /// JSON-RPC version
     jsonrpc: String,
     /// RPC result
+    #[serde(default)]
     result: Value,
+    /// RPC error, set if the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
     /// RPC request ID
     id: u64,
+    /// Time it took to get the response
+    #[serde(skip)]
+    elapsed: Duration,
+}
+
+/// RPC error object
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RpcError {
+    /// Error code
+    pub code: i64,
+    /// Error message
+    pub message: String,
+    /// Additional error data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
 }
 
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "code {}: {}", self.code, self.message)
+    }
+}
+
+impl RpcResponse {
+    /// Successful response to the request with the given id
+    pub fn new(id: u64, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            result,
+            error: None,
+            id,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Id of the request this responds to
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Time it took to get the response
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Result of the request
+    pub fn result(&self) -> &Value {
+        &self.result
+    }
+}
+
+/// Mode of a dry run, the requests are never sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DryRun {
+    /// The requests are serialized, then answered after a fixed delay standing for the network
+    Noop,
+    /// The requests are serialized and answered right away, so that the run only reflects
+    /// the client-side cost (payloads, encoding and pacing)
+    Realistic,
+}
+
+impl DryRun {
+    /// Delay of the answer to a noop request
+    pub fn delay(self) -> Duration {
+        match self {
+            DryRun::Noop => Duration::from_millis(5),
+            DryRun::Realistic => Duration::ZERO,
+        }
+    }
+}
+
+/// Answer the request without sending it, after serializing it like a real request
 pub async fn send_noop(
     rpc_url: &str,
     rpc_request: RpcRequest,
+    dry_run: DryRun,
 ) -> Result<RpcResponse, TestrpcError> {
     let as_json = serde_json::to_string(&rpc_request)
         .map_err(|e| TestrpcError::RpcError(format!("Failed to serialize request: {e}")))?;
     tracing::info!(
-        "Sending noop request with {} bytes to {}",
+        "Sending noop request {} with {} bytes to {}",
+        rpc_request.id,
         as_json.len(),
         rpc_url
     );
-    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let start_time = std::time::Instant::now();
+    noop_delay(dry_run).await;
     Ok(RpcResponse {
         jsonrpc: "2.0".to_string(),
         result: serde_json::json!({}),
+        error: None,
         id: rpc_request.id,
+        elapsed: start_time.elapsed(),
     })
 }
 
-/// Sends requests to the RPC server
+/// Answer the batch without sending it, after serializing it like a real batch
+pub async fn send_batch_noop(
+    rpc_url: &str,
+    rpc_requests: Vec<RpcRequest>,
+    dry_run: DryRun,
+) -> Result<Vec<Result<RpcResponse, TestrpcError>>, TestrpcError> {
+    let as_json = serde_json::to_string(&rpc_requests)
+        .map_err(|e| TestrpcError::RpcError(format!("Failed to serialize request: {e}")))?;
+    tracing::info!(
+        "Sending noop batch of {} requests with {} bytes to {}",
+        rpc_requests.len(),
+        as_json.len(),
+        rpc_url
+    );
+    let start_time = std::time::Instant::now();
+    noop_delay(dry_run).await;
+    let elapsed = start_time.elapsed();
+    Ok(rpc_requests
+        .iter()
+        .map(|rpc_request| {
+            Ok(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result: serde_json::json!({}),
+                error: None,
+                id: rpc_request.id,
+                elapsed,
+            })
+        })
+        .collect())
+}
+
+/// Wait for the answer to a noop request, per the mode of the dry run
+async fn noop_delay(dry_run: DryRun) {
+    let delay = dry_run.delay();
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Sends requests to the RPC server, using the shared client
 pub async fn send(
     rpc_url: &str,
     req_id: u64,
     method: &str,
     params: Value,
-    timeout: Option<std::time::Duration>,
+    timeout: Option<Duration>,
 ) -> Result<RpcResponse, TestrpcError> {
-    let rpc_request = RpcRequest {
-        jsonrpc: "2.0".to_string(),
-        method: method.to_string(),
-        params,
-        id: req_id,
-    };
-    if env::var("DRY_RUN").is_ok() {
-        return send_noop(rpc_url, rpc_request).await;
+    client()?
+        .send(rpc_url, req_id, method, params, timeout)
+        .await
+}
+
+/// Sends requests to the RPC server over WebSocket, using the shared client.
+/// See `Client::send_ws`.
+pub async fn send_ws(
+    rpc_url: &str,
+    req_id: u64,
+    method: &str,
+    params: Value,
+    timeout: Option<Duration>,
+) -> Result<RpcResponse, TestrpcError> {
+    client()?
+        .send_ws(rpc_url, req_id, method, params, timeout)
+        .await
+}
+
+/// Sends a JSON-RPC batch to the RPC server, using the shared client.
+/// See `Client::send_batch`.
+pub async fn send_batch(
+    rpc_url: &str,
+    rpc_requests: Vec<RpcRequest>,
+    timeout: Option<Duration>,
+) -> Result<Vec<Result<RpcResponse, TestrpcError>>, TestrpcError> {
+    client()?.send_batch(rpc_url, rpc_requests, timeout).await
+}
+
+impl Client {
+    /// Mode of the dry run of the client, None for a real run
+    pub fn dry_run(&self) -> Option<DryRun> {
+        self.dry_run
     }
-    let client = reqwest::ClientBuilder::new()
-        .timeout(timeout.unwrap_or(std::time::Duration::from_secs(15)))
-        .build()
-        .map_err(|e| TestrpcError::RpcError(format!("Failed to build client: {e}")))?;
 
-    let start_time = std::time::Instant::now();
+    pub fn new(options: &ClientOptions) -> Result<Self, TestrpcError> {
+        let headers = header_map(&options.headers)?;
+        let mut builder = reqwest::ClientBuilder::new()
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(TCP_KEEPALIVE);
+        if options.http2 {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(ca_cert) = &options.ca_cert {
+            let pem = std::fs::read(ca_cert)
+                .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), ca_cert.clone()))?;
+            let cert = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| TestrpcError::LoadConfigError(e.to_string(), ca_cert.clone()))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        if !headers.is_empty() {
+            builder = builder.default_headers(headers.clone());
+        }
+        let http = builder
+            .build()
+            .map_err(|e| TestrpcError::RpcError(format!("Failed to build client: {e}")))?;
+        Ok(Self {
+            http,
+            headers,
+            ws: Default::default(),
+            rpc_path: options.rpc_path.clone(),
+            dry_run: options.dry_run,
+        })
+    }
 
-    let response = client
-        .post(rpc_url)
-        .json(&rpc_request)
-        .send()
-        .await
-        .map_err(|e| TestrpcError::RpcError(format!("Failed to make request: {e}")))?;
+    /// Fetches the body of a plain GET request (e.g. to a coordinator), with the headers and
+    /// root certificate of the client. Fails on an error status.
+    pub async fn get(&self, url: &str, timeout: Option<Duration>) -> Result<String, TestrpcError> {
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        self.http
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| request_error(e, timeout, "Failed to make request"))?
+            .text()
+            .await
+            .map_err(|e| request_error(e, timeout, "Failed to read response"))
+    }
 
-    tracing::info!(
-        "Got RPC response after {}ms",
-        start_time.elapsed().as_millis()
-    );
+    /// Sends requests to the RPC server
+    /// The errors carry the endpoint, id and method of the request, see `TestrpcError::RequestFailed`.
+    pub async fn send(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        method: &str,
+        params: Value,
+        timeout: Option<Duration>,
+    ) -> Result<RpcResponse, TestrpcError> {
+        if ws::is_ws(rpc_url) {
+            return self.send_ws(rpc_url, req_id, method, params, timeout).await;
+        }
+        self.send_http(rpc_url, req_id, method, params, timeout)
+            .await
+            .map_err(|e| e.for_request(rpc_url, req_id, method))
+    }
 
-    tracing::debug!("Raw RPC response: {:?}", response);
+    /// Sends a request to the RPC server over HTTP
+    async fn send_http(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        method: &str,
+        params: Value,
+        timeout: Option<Duration>,
+    ) -> Result<RpcResponse, TestrpcError> {
+        let rpc_request = RpcRequest::new(req_id, method, params);
+        if let Some(dry_run) = self.dry_run {
+            return send_noop(rpc_url, rpc_request, dry_run).await;
+        }
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
 
-    let response: RpcResponse = response
-        .json()
-        .await
-        .map_err(|e| TestrpcError::RpcError(format!("Failed to parse response: {e}")))?;
+        let start_time = std::time::Instant::now();
+
+        let response = self
+            .http
+            .post(with_path(rpc_url, self.rpc_path.as_deref()))
+            .timeout(timeout)
+            .json(&rpc_request)
+            .send()
+            .await
+            .map_err(|e| request_error(e, timeout, "Failed to make request"))?;
+
+        let elapsed = start_time.elapsed();
+        tracing::info!(
+            "Got RPC response to request {} after {}ms",
+            req_id,
+            elapsed.as_millis()
+        );
+
+        tracing::debug!("Raw RPC response: {:?}", response);
+
+        let mut response: RpcResponse = response
+            .json()
+            .await
+            .map_err(|e| request_error(e, timeout, "Failed to parse response"))?;
+        response.elapsed = elapsed;
+
+        tracing::debug!("RPC response: {:?}", response);
+
+        if let Some(error) = response.error {
+            return Err(TestrpcError::RpcError(error.to_string()));
+        }
+
+        Ok(response)
+    }
+
+    /// Sends a JSON-RPC batch to the RPC server.
+    /// Returns the outcome of each request in the order of `rpc_requests`,
+    /// a request fails if its response carries an error or is missing from the batch.
+    pub async fn send_batch(
+        &self,
+        rpc_url: &str,
+        rpc_requests: Vec<RpcRequest>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Result<RpcResponse, TestrpcError>>, TestrpcError> {
+        if let Some(dry_run) = self.dry_run {
+            return send_batch_noop(rpc_url, rpc_requests, dry_run).await;
+        }
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        if ws::is_ws(rpc_url) {
+            let responses = self
+                .ws_roundtrip(rpc_url, rpc_requests.clone(), true, timeout)
+                .await?
+                .into_iter()
+                .flatten()
+                .map(|response| (response.id, response))
+                .collect::<HashMap<_, _>>();
+            return Ok(batch_results(rpc_url, &rpc_requests, responses));
+        }
+
+        let start_time = std::time::Instant::now();
+
+        let response = self
+            .http
+            .post(with_path(rpc_url, self.rpc_path.as_deref()))
+            .timeout(timeout)
+            .json(&rpc_requests)
+            .send()
+            .await
+            .map_err(|e| request_error(e, timeout, "Failed to make request"))?;
+
+        let elapsed = start_time.elapsed();
+        tracing::info!("Got RPC batch response after {}ms", elapsed.as_millis());
 
-    tracing::debug!("RPC response: {:?}", response);
+        let responses: Vec<RpcResponse> = response
+            .json()
+            .await
+            .map_err(|e| request_error(e, timeout, "Failed to parse response"))?;
 
-    Ok(response)
+        tracing::debug!("RPC batch responses: {:?}", responses);
+
+        let responses = responses
+            .into_iter()
+            .map(|mut response| {
+                response.elapsed = elapsed;
+                (response.id, response)
+            })
+            .collect::<HashMap<_, _>>();
+
+        Ok(batch_results(rpc_url, &rpc_requests, responses))
+    }
+
+    /// Sends a request over the WebSocket connection to the RPC server, opening it if needed.
+    /// Concurrent requests share the connection, their responses are matched by id.
+    pub async fn send_ws(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        method: &str,
+        params: Value,
+        timeout: Option<Duration>,
+    ) -> Result<RpcResponse, TestrpcError> {
+        self.send_ws_request(rpc_url, req_id, method, params, timeout)
+            .await
+            .map_err(|e| e.for_request(rpc_url, req_id, method))
+    }
+
+    async fn send_ws_request(
+        &self,
+        rpc_url: &str,
+        req_id: u64,
+        method: &str,
+        params: Value,
+        timeout: Option<Duration>,
+    ) -> Result<RpcResponse, TestrpcError> {
+        let rpc_request = RpcRequest::new(req_id, method, params);
+        if let Some(dry_run) = self.dry_run {
+            return send_noop(rpc_url, rpc_request, dry_run).await;
+        }
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let response = self
+            .ws_roundtrip(rpc_url, vec![rpc_request], false, timeout)
+            .await?
+            .pop()
+            .flatten()
+            .ok_or(TestrpcError::RequestError(
+                "WebSocket connection closed before the response".to_string(),
+            ))?;
+        tracing::info!(
+            "Got RPC response to request {} after {}ms",
+            req_id,
+            response.elapsed.as_millis()
+        );
+        tracing::debug!("RPC response: {:?}", response);
+
+        if let Some(error) = response.error {
+            return Err(TestrpcError::RpcError(error.to_string()));
+        }
+
+        Ok(response)
+    }
+
+    /// Returns the open WebSocket connection to the url, connecting if there is none.
+    /// Only the requests to the same url wait for the connection, a closed one is replaced.
+    async fn ws_connection(&self, rpc_url: &str) -> Result<Arc<ws::Connection>, TestrpcError> {
+        let slot = self
+            .ws
+            .lock()
+            .unwrap()
+            .entry(rpc_url.to_string())
+            .or_default()
+            .clone();
+        let mut slot = slot.lock().await;
+        if let Some(connection) = slot.as_ref().filter(|connection| !connection.is_closed()) {
+            return Ok(connection.clone());
+        }
+        let url = with_path(rpc_url, self.rpc_path.as_deref());
+        let connection = Arc::new(ws::Connection::connect(&url, &self.headers).await?);
+        if let Some(closed) = slot.replace(connection.clone()) {
+            closed.close();
+        }
+        Ok(connection)
+    }
+
+    /// Sends a request, or a batch if `batch` is set, over WebSocket, returning the response to
+    /// each request (in the order of `rpc_requests`), None if the connection closed before it arrived
+    async fn ws_roundtrip(
+        &self,
+        rpc_url: &str,
+        rpc_requests: Vec<RpcRequest>,
+        batch: bool,
+        timeout: Duration,
+    ) -> Result<Vec<Option<RpcResponse>>, TestrpcError> {
+        let start_time = std::time::Instant::now();
+        let timed_out =
+            || TestrpcError::RequestError(format!("Request timed out after {timeout:?}"));
+        let connection = tokio::time::timeout(timeout, self.ws_connection(rpc_url))
+            .await
+            .map_err(|_| timed_out())??;
+        let (ids, receivers): (Vec<_>, Vec<_>) =
+            connection.send(rpc_requests, batch)?.into_iter().unzip();
+        let remaining = timeout.saturating_sub(start_time.elapsed());
+        let Ok(responses) = tokio::time::timeout(remaining, join_all(receivers)).await else {
+            connection.cancel(&ids);
+            return Err(timed_out());
+        };
+        let elapsed = start_time.elapsed();
+        Ok(responses
+            .into_iter()
+            .map(|response| {
+                response.ok().map(|mut response| {
+                    response.elapsed = elapsed;
+                    response
+                })
+            })
+            .collect())
+    }
+}
+
+/// WebSocket connection to an endpoint, None until the first request connects
+type WsSlot = Arc<tokio::sync::Mutex<Option<Arc<ws::Connection>>>>;
+
+/// Outcome of each request of a batch, in the order of `rpc_requests`, given the responses by id
+fn batch_results(
+    rpc_url: &str,
+    rpc_requests: &[RpcRequest],
+    mut responses: HashMap<u64, RpcResponse>,
+) -> Vec<Result<RpcResponse, TestrpcError>> {
+    rpc_requests
+        .iter()
+        .map(|rpc_request| {
+            match responses.remove(&rpc_request.id) {
+                Some(RpcResponse {
+                    error: Some(error), ..
+                }) => Err(TestrpcError::RpcError(error.to_string())),
+                Some(response) => Ok(response),
+                None => Err(TestrpcError::RpcError(
+                    "Missing response in the batch".to_string(),
+                )),
+            }
+            .map_err(|e| e.for_request(rpc_url, rpc_request.id, &rpc_request.method))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_response() {
+        let raw = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"},"id":1}"#;
+        let response: RpcResponse = serde_json::from_str(raw).unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert_eq!(error.message, "Invalid params");
+        assert!(error.data.is_none());
+        assert_eq!(error.to_string(), "code -32602: Invalid params");
+    }
+
+    #[test]
+    fn test_with_scheme() {
+        assert_eq!(with_scheme("localhost:5000"), "http://localhost:5000");
+        assert_eq!(
+            with_scheme("http://localhost:5000"),
+            "http://localhost:5000"
+        );
+        assert_eq!(with_scheme("https://node:443"), "https://node:443");
+    }
+
+    #[test]
+    fn test_header_map() {
+        let headers = HashMap::from([("Authorization".to_string(), "Bearer secret".to_string())]);
+        let map = header_map(&headers).unwrap();
+        let value = map.get("authorization").unwrap();
+        assert_eq!(value, "Bearer secret");
+        assert!(value.is_sensitive());
+        assert!(!format!("{map:?}").contains("secret"));
+
+        let headers = HashMap::from([("bad header".to_string(), "value".to_string())]);
+        assert!(header_map(&headers).is_err());
+    }
+
+    #[test]
+    fn test_dry_run() {
+        assert!(DryRun::Realistic.delay().is_zero());
+        assert!(!DryRun::Noop.delay().is_zero());
+    }
+
+    #[test]
+    fn test_with_path() {
+        assert_eq!(with_path("localhost:5000", None), "http://localhost:5000");
+        assert_eq!(
+            with_path("localhost:5000", Some("/rpc")),
+            "http://localhost:5000/rpc"
+        );
+        assert_eq!(
+            with_path("http://localhost:5000/", Some("v1")),
+            "http://localhost:5000/v1"
+        );
+        // an explicit path is kept
+        assert_eq!(
+            with_path("https://node:443/custom", Some("/rpc")),
+            "https://node:443/custom"
+        );
+        assert_eq!(
+            with_path("ws://node:8546", Some("/rpc")),
+            "ws://node:8546/rpc"
+        );
+        assert_eq!(
+            with_path("http://host:8545?key=x", Some("/rpc")),
+            "http://host:8545/rpc?key=x"
+        );
+        assert_eq!(
+            with_path("http://host:8545/custom?key=x", Some("/rpc")),
+            "http://host:8545/custom?key=x"
+        );
+    }
+
+    #[test]
+    fn test_missing_ca_cert() {
+        let options = ClientOptions {
+            ca_cert: Some("/nonexistent/ca.pem".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Client::new(&options),
+            Err(TestrpcError::LoadConfigError(_, _))
+        ));
+    }
 }
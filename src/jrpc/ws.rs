@@ -0,0 +1,190 @@
+/// WebSocket transport, the requests in flight to an endpoint share one socket
+use futures::{SinkExt as _, StreamExt as _};
+use reqwest::header::HeaderMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest as _, Message};
+
+use super::{RpcRequest, RpcResponse};
+use crate::common::TestrpcError;
+
+/// Returns true if the URL has a WebSocket scheme
+pub fn is_ws(url: &str) -> bool {
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+/// Request waiting for its response, with the id it was given by its sender
+type Pending = (u64, oneshot::Sender<RpcResponse>);
+
+/// WebSocket connection to an endpoint, responses are routed to the waiting requests by id.
+/// The requests are sent with their own id, unless a request in flight already has it: then the
+/// request is sent with another id, so that it gets its own response, and its response gets
+/// back its original id.
+#[derive(Debug)]
+pub struct Connection {
+    outgoing: mpsc::UnboundedSender<Message>,
+    /// Requests waiting for their response, by the id they were sent with
+    pending: Arc<Mutex<HashMap<u64, Pending>>>,
+    closed: Arc<AtomicBool>,
+    /// Next id to send a request with if its own is in flight, see `Connection::send`
+    next_id: AtomicU64,
+    /// Task reading the responses, aborted when the connection is closed
+    reader: JoinHandle<()>,
+}
+
+impl Connection {
+    /// Open a connection to the given URL, with the given handshake headers
+    pub async fn connect(url: &str, headers: &HeaderMap) -> Result<Self, TestrpcError> {
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| TestrpcError::RequestError(format!("Invalid WebSocket url: {e}")))?;
+        request.headers_mut().extend(headers.clone());
+        let (stream, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| TestrpcError::RequestError(format!("Failed to connect to {url}: {e}")))?;
+        let (mut sink, mut stream) = stream.split();
+        let (outgoing, mut rx) = mpsc::unbounded_channel::<Message>();
+        let pending = Arc::new(Mutex::new(HashMap::<u64, Pending>::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let writer_closed = closed.clone();
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                if let Err(e) = sink.send(message).await {
+                    tracing::warn!("Failed to write to WebSocket: {}", e);
+                    break;
+                }
+            }
+            writer_closed.store(true, Ordering::SeqCst);
+        });
+
+        let reader_pending = pending.clone();
+        let reader_closed = closed.clone();
+        let url = url.to_string();
+        let reader = tokio::spawn(async move {
+            while let Some(message) = stream.next().await {
+                let text = match message {
+                    Ok(Message::Text(text)) => text,
+                    Ok(Message::Close(_)) => break,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        tracing::warn!("Failed to read from WebSocket {}: {}", url, e);
+                        break;
+                    }
+                };
+                let responses = match serde_json::from_str::<Vec<RpcResponse>>(text.as_str()) {
+                    Ok(responses) => responses,
+                    Err(_) => match serde_json::from_str::<RpcResponse>(text.as_str()) {
+                        Ok(response) => vec![response],
+                        Err(e) => {
+                            tracing::warn!("Invalid response from WebSocket {}: {}", url, e);
+                            continue;
+                        }
+                    },
+                };
+                let mut pending = reader_pending.lock().unwrap();
+                for mut response in responses {
+                    match pending.remove(&response.id) {
+                        Some((id, tx)) => {
+                            response.id = id;
+                            let _ = tx.send(response);
+                        }
+                        None => tracing::debug!("Unexpected response {} from {}", response.id, url),
+                    }
+                }
+            }
+            reader_closed.store(true, Ordering::SeqCst);
+            // dropping the senders fails the requests still waiting for a response
+            reader_pending.lock().unwrap().clear();
+        });
+
+        Ok(Self {
+            outgoing,
+            pending,
+            closed,
+            // away from the ids of the caller, which grow from the iteration
+            next_id: AtomicU64::new(rand::random()),
+            reader,
+        })
+    }
+
+    /// Whether the connection was closed, it has to be reopened then
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.outgoing.is_closed()
+    }
+
+    /// Close the connection: stop reading the responses and fail the requests still waiting
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.reader.abort();
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Send the requests, as a batch if `batch` is set, and return the id each request was sent
+    /// with (to cancel it) and a receiver for its response, in the order of `requests`
+    pub fn send(
+        &self,
+        mut requests: Vec<RpcRequest>,
+        batch: bool,
+    ) -> Result<Vec<(u64, oneshot::Receiver<RpcResponse>)>, TestrpcError> {
+        let receivers = {
+            let mut pending = self.pending.lock().unwrap();
+            requests
+                .iter_mut()
+                .map(|request| {
+                    let (tx, rx) = oneshot::channel();
+                    let mut id = request.id;
+                    while pending.contains_key(&id) {
+                        id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if id != request.id {
+                        tracing::debug!("Request {} already in flight, sent as {}", request.id, id);
+                    }
+                    pending.insert(id, (request.id, tx));
+                    request.id = id;
+                    (id, rx)
+                })
+                .collect::<Vec<_>>()
+        };
+        let body = match (batch, requests.as_slice()) {
+            (false, [request]) => serde_json::to_string(request),
+            _ => serde_json::to_string(&requests),
+        };
+        let sent = body
+            .map_err(|e| TestrpcError::RpcError(format!("Failed to serialize request: {e}")))
+            .and_then(|body| {
+                self.outgoing.send(Message::text(body)).map_err(|_| {
+                    TestrpcError::RequestError("WebSocket connection closed".to_string())
+                })
+            });
+        if let Err(e) = sent {
+            self.cancel(&receivers.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+            return Err(e);
+        }
+        Ok(receivers)
+    }
+
+    /// Forget the requests that won't wait for their response anymore, by the id they were sent with
+    pub fn cancel(&self, ids: &[u64]) {
+        let mut pending = self.pending.lock().unwrap();
+        for id in ids {
+            pending.remove(id);
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // the writer stops with the outgoing channel, the reader would wait for the server
+        self.reader.abort();
+    }
+}
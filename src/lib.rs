@@ -1,22 +1,27 @@
 pub mod adapters;
 pub mod common;
+pub mod compare;
 pub mod config;
 pub mod ctx;
+pub mod health;
 pub mod jrpc;
 pub mod logging;
+pub mod metrics;
+pub mod mock;
+pub mod progress;
+pub mod rng;
 pub mod runner;
 pub mod signal;
 
 #[cfg(test)]
 mod test {
-    use std::{env, sync::Arc};
+    use std::sync::Arc;
 
     use super::*;
     use tokio::time::{sleep, Duration};
 
     #[tokio::test]
     async fn test_e2e_dry_run() {
-        env::set_var("DRY_RUN", "true"); // run in dry-run mode
         let raw_cfg_yaml: &str = r#"
 interval: 1
 iterations: 4
@@ -41,17 +46,19 @@ rounds:
         txs: 10
         tx_size: 1000
         "#;
-        let cfg = config::parse_config_yaml(raw_cfg_yaml).unwrap();
+        let mut cfg = config::parse_config_yaml(raw_cfg_yaml).unwrap();
+        // run in dry-run mode
+        cfg.dry_run = Some(jrpc::DryRun::Noop);
         let ctx = Arc::new(ctx::Context::new());
         let rpc_urls = runner::load_endpoints(cfg.clone()).await.unwrap();
         let ctx_cloned = ctx.clone();
         let handle = tokio::spawn(async move {
             // wait for the test to complete
             // use a timeout of <interval> * <interations> + buffer
-            let buffer = 1;
-            let timeout = 1 * 4 + buffer;
+            let (interval, iterations, buffer) = (1, 4, 1);
+            let timeout = interval * iterations + buffer;
             sleep(Duration::from_secs(timeout)).await;
-            ctx_cloned.stop();
+            ctx_cloned.stop(ctx::StopReason::Signal);
         });
         tokio::select! {
             _ = handle => {
@@ -59,9 +66,11 @@ rounds:
             }
             Ok(results) = runner::run(ctx, cfg, rpc_urls) => {
                 assert_eq!(results.len(), 4);
-                for result in results {
+                for (i, result) in results.into_iter().enumerate() {
+                    let tx_size = if i % 2 == 0 { 100 } else { 1000 };
                     assert_eq!(result.sent, 20);
                     assert_eq!(result.failed, 0);
+                    assert_eq!(result.bytes_sent, 20 * tx_size);
                 }
             }
         };
@@ -1,70 +1,304 @@
-use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+use std::{path::Path, str::FromStr};
+use tracing::Level;
+use tracing_subscriber::{
+    filter::Targets, fmt::format::FmtSpan, layer::SubscriberExt as _, util::SubscriberInitExt as _,
+    EnvFilter, Layer as _,
+};
 
 use tracing_appender::non_blocking::WorkerGuard;
 
-/// Initializes logging
-pub fn initialize_logging() -> WorkerGuard {
-    // Parse the `RUST_LOG_SPAN_EVENTS` environment variable
-    let span_event_filter = match std::env::var("RUST_LOG_SPAN_EVENTS") {
-        Ok(val) => val
-            .split(',')
-            .map(|s| match s.trim() {
-                "new" => FmtSpan::NEW,
-                "enter" => FmtSpan::ENTER,
-                "exit" => FmtSpan::EXIT,
-                "close" => FmtSpan::CLOSE,
-                "active" => FmtSpan::ACTIVE,
-                "full" => FmtSpan::FULL,
-                _ => FmtSpan::NONE,
-            })
-            .fold(FmtSpan::NONE, |acc, x| acc | x),
-        Err(_) => FmtSpan::NONE,
+use crate::common::FlowResults;
+
+mod otlp;
+pub mod perf;
+mod rolling;
+
+/// Target of the logs emitted for every request, silenced in quiet mode
+pub const REQUESTS_TARGET: &str = "testrpc::jrpc";
+
+/// Target of the summary event emitted at the end of a run and of the `perf::PerfEvent`s,
+/// for the perf tooling
+pub const PERF_TARGET: &str = "testrpc::perf";
+
+/// Keeps the logging running, the logs are flushed and the OTLP exports finished when dropped
+pub struct LoggingGuard {
+    otlp: Option<otlp::Otlp>,
+    _log_guard: WorkerGuard,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        if let Some(otlp) = &self.otlp {
+            otlp.shutdown();
+        }
+    }
+}
+
+/// Format of the logs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Rotation of the log file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    #[default]
+    Never,
+    /// A new file suffixed with the date and hour every hour
+    Hourly,
+    /// A new file suffixed with the date every day
+    Daily,
+    /// The file is rolled once it reaches the given size in bytes
+    Size(u64),
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    /// Parse `never`, `hourly`, `daily` or `size:<MB>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(LogRotation::Never),
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            _ => match s.strip_prefix("size:") {
+                Some(mb) => match mb.parse::<u64>() {
+                    Ok(mb) if mb > 0 => mb
+                        .checked_mul(1024 * 1024)
+                        .map(LogRotation::Size)
+                        .ok_or_else(|| format!("log rotation size {mb} MB is too large")),
+                    _ => Err(format!(
+                        "invalid log rotation size {mb}, expected a number of MB"
+                    )),
+                },
+                None => Err(format!(
+                    "invalid log rotation {s}, expected never, hourly, daily or size:<MB>"
+                )),
+            },
+        }
+    }
+}
+
+/// Configuration of the logging
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    /// Filter directives, e.g. `info` or `testrpc=debug,warn` (see `EnvFilter`)
+    pub level: String,
+    /// File to write the logs to, stdout if None
+    pub file: Option<String>,
+    pub rotation: LogRotation,
+    pub format: LogFormat,
+    /// Span events to log, see `parse_span_events`
+    pub span_events: FmtSpan,
+    /// Endpoint of the OTLP collector to export the spans of testrpc to
+    pub otlp_endpoint: Option<String>,
+    /// Only log the warnings and errors of `REQUESTS_TARGET`, whatever the level
+    pub quiet: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: String::new(),
+            file: None,
+            rotation: LogRotation::default(),
+            format: LogFormat::default(),
+            span_events: FmtSpan::NONE,
+            otlp_endpoint: None,
+            quiet: false,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Configuration from the `RUST_LOG`, `RUST_LOG_FILE`, `RUST_LOG_ROTATION`, `RUST_LOG_FORMAT`,
+    /// `RUST_LOG_SPAN_EVENTS` and `OTEL_EXPORTER_OTLP_ENDPOINT` environment variables
+    pub fn from_env() -> Self {
+        Self {
+            level: std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_default(),
+            file: std::env::var("RUST_LOG_FILE").ok(),
+            rotation: std::env::var("RUST_LOG_ROTATION")
+                .ok()
+                .and_then(|rotation| rotation.parse().ok())
+                .unwrap_or_default(),
+            format: match std::env::var("RUST_LOG_FORMAT") {
+                Ok(format) if format == "json" => LogFormat::Json,
+                _ => LogFormat::Text,
+            },
+            span_events: std::env::var("RUST_LOG_SPAN_EVENTS")
+                .map(|val| parse_span_events(&val))
+                .unwrap_or(FmtSpan::NONE),
+            otlp_endpoint: std::env::var(otlp::ENDPOINT_ENV).ok(),
+            quiet: false,
+        }
+    }
+}
+
+/// Parse a comma separated list of span events (`new`, `enter`, `exit`, `close`, `active`, `full`),
+/// unknown events are ignored
+pub fn parse_span_events(val: &str) -> FmtSpan {
+    val.split(',')
+        .map(|s| match s.trim() {
+            "new" => FmtSpan::NEW,
+            "enter" => FmtSpan::ENTER,
+            "exit" => FmtSpan::EXIT,
+            "close" => FmtSpan::CLOSE,
+            "active" => FmtSpan::ACTIVE,
+            "full" => FmtSpan::FULL,
+            _ => FmtSpan::NONE,
+        })
+        .fold(FmtSpan::NONE, |acc, x| acc | x)
+}
+
+/// Initializes logging from the environment, see `LoggingConfig::from_env`
+pub fn initialize_logging() -> LoggingGuard {
+    initialize_logging_with(LoggingConfig::from_env())
+}
+
+/// Initializes logging with the given configuration.
+/// With an `otlp_endpoint`, the spans of testrpc are also exported to that OTLP collector,
+/// it must be called within a tokio runtime then.
+pub fn initialize_logging_with(config: LoggingConfig) -> LoggingGuard {
+    let (log_writer, guard) = get_log_file_writer(config.file.as_deref(), &config.rotation);
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_writer(log_writer)
+        .with_span_events(config.span_events);
+    let fmt_layer = match config.format {
+        LogFormat::Json => fmt_layer.json().boxed(),
+        LogFormat::Text => fmt_layer.boxed(),
     };
 
-    let (log_writer, guard) = get_log_file_writer();
-
-    // Conditionally initialize in `json` mode
-    if std::env::var("RUST_LOG_FORMAT") == Ok("json".to_string()) {
-        match tracing_subscriber::fmt()
-            .with_writer(log_writer)
-            .with_env_filter(EnvFilter::from_default_env())
-            .with_span_events(span_event_filter)
-            .json()
-            .try_init()
-        {
-            Ok(()) => tracing::info!("Logging initialized"),
-            Err(err) => eprintln!("Failed to initialize logging: {err}"),
-        };
-    } else {
-        match tracing_subscriber::fmt()
-            .with_writer(log_writer)
-            .with_env_filter(EnvFilter::from_default_env())
-            .with_span_events(span_event_filter)
-            .try_init()
-        {
-            Ok(()) => tracing::info!("Logging initialized"),
-            Err(err) => eprintln!("Failed to initialize logging: {err}"),
-        };
+    let (otlp, otlp_error) = match &config.otlp_endpoint {
+        Some(endpoint) => match otlp::Otlp::init(endpoint) {
+            Ok(otlp) => (Some(otlp), None),
+            Err(e) => (None, Some(e)),
+        },
+        None => (None, None),
     };
+    // Only the spans of testrpc are exported, not the ones of the exporter itself
+    let otel_layer = otlp.as_ref().map(|otlp| {
+        tracing_opentelemetry::layer()
+            .with_tracer(otlp.tracer())
+            .with_filter(Targets::new().with_target("testrpc", Level::INFO))
+    });
+
+    let mut level = config.level.clone();
+    if config.quiet {
+        level.push_str(&format!(",{REQUESTS_TARGET}=warn"));
+    }
+    match tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(EnvFilter::builder().parse_lossy(&level)))
+        .with(otel_layer)
+        .try_init()
+    {
+        Ok(()) => tracing::info!("Logging initialized"),
+        Err(err) => eprintln!("Failed to initialize logging: {err}"),
+    };
+    if let Some(e) = otlp_error {
+        tracing::warn!("OTLP export disabled: {}", e);
+    } else if otlp.is_some() {
+        tracing::info!("Exporting spans and results to the OTLP collector");
+    }
     // Return the guard to ensure logs are flushed
-    guard
+    LoggingGuard {
+        otlp,
+        _log_guard: guard,
+    }
+}
+
+/// Log the summary of the run as a single `testrpc::perf` event, and export the results with the
+/// final metrics snapshot if the OTLP export is enabled.
+/// The field names are relied upon by the perf tooling, see the README before renaming them.
+pub fn record_results(results: &FlowResults) {
+    let duration_secs = results.total_time.as_secs_f64();
+    tracing::info!(
+        target: PERF_TARGET,
+        iterations = results.total_iterations,
+        sent = results.total.sent,
+        failed = results.total.failed,
+        bytes_sent = results.total.bytes_sent,
+        bytes_failed = results.total.bytes_failed,
+        duration_secs,
+        tx_per_sec = tx_rate(results.total.sent, duration_secs),
+        "Run summary"
+    );
+    otlp::record_results(results);
 }
 
-/// Returns a log file writer, using the `RUST_LOG_FILE` environment variable if set or defaults to stdout.
-/// The log file is rolled daily.
-fn get_log_file_writer() -> (tracing_appender::non_blocking::NonBlocking, WorkerGuard) {
-    if let Ok(log_file_path) = std::env::var("RUST_LOG_FILE") {
-        let (directory, prefix) = if let Some(split_at) = log_file_path.rfind('/') {
-            log_file_path.split_at(split_at)
-        } else {
-            // Defaults to current directory
-            ("./", log_file_path.as_str())
+/// Transactions sent per second, 0 for an empty duration
+fn tx_rate(sent: usize, secs: f64) -> f64 {
+    if secs > 0.0 {
+        sent as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+/// Returns a log file writer, using the given log file if set or defaults to stdout.
+/// The log file is rolled according to `rotation`.
+fn get_log_file_writer(
+    log_file_path: Option<&str>,
+    rotation: &LogRotation,
+) -> (tracing_appender::non_blocking::NonBlocking, WorkerGuard) {
+    if let Some(log_file_path) = log_file_path {
+        let path = Path::new(log_file_path);
+        // Defaults to current directory
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("./"),
         };
-        let writer = tracing_appender::rolling::never(directory, prefix);
-        let (non_blocking, guard) = tracing_appender::non_blocking(writer);
-        return (non_blocking, guard);
+        let prefix = path.file_name().unwrap_or(path.as_os_str());
+        let writer: Box<dyn std::io::Write + Send> = match rotation {
+            LogRotation::Never => Box::new(tracing_appender::rolling::never(directory, prefix)),
+            LogRotation::Hourly => Box::new(tracing_appender::rolling::hourly(directory, prefix)),
+            LogRotation::Daily => Box::new(tracing_appender::rolling::daily(directory, prefix)),
+            LogRotation::Size(max_size) => match rolling::SizeRollingWriter::new(path, *max_size) {
+                Ok(writer) => Box::new(writer),
+                Err(e) => {
+                    eprintln!("Failed to open log file {log_file_path}: {e}, logging to stdout");
+                    Box::new(std::io::stdout())
+                }
+            },
+        };
+        return tracing_appender::non_blocking(writer);
     }
     // Defaults to stdout if no log file is specified
     let (non_blocking, guard) = tracing_appender::non_blocking(std::io::stdout());
     (non_blocking, guard)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_rate() {
+        assert_eq!(tx_rate(100, 4.0), 25.0);
+        assert_eq!(tx_rate(100, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_parse_span_events() {
+        assert_eq!(
+            parse_span_events("new, close"),
+            FmtSpan::NEW | FmtSpan::CLOSE
+        );
+        assert_eq!(parse_span_events("full"), FmtSpan::FULL);
+        assert_eq!(parse_span_events("unknown"), FmtSpan::NONE);
+    }
+
+    #[test]
+    fn test_log_rotation() {
+        assert_eq!("daily".parse(), Ok(LogRotation::Daily));
+        assert_eq!("size:10".parse(), Ok(LogRotation::Size(10 * 1024 * 1024)));
+        assert!("size:0".parse::<LogRotation>().is_err());
+        assert!(format!("size:{}", u64::MAX / 1024)
+            .parse::<LogRotation>()
+            .is_err());
+        assert!("weekly".parse::<LogRotation>().is_err());
+    }
+}
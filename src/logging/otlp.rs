@@ -0,0 +1,106 @@
+/// Export of the round spans and of the results to an OpenTelemetry collector over OTLP (gRPC)
+use opentelemetry::{metrics::Meter, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::WithExportConfig as _;
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    runtime,
+    trace::{Tracer, TracerProvider},
+    Resource,
+};
+use std::time::Duration;
+
+use crate::common::FlowResults;
+
+/// Environment variable holding the endpoint of the collector, e.g. `http://localhost:4317`
+pub const ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+/// Timeout of an export, kept short so that an unreachable collector doesn't hold the exit
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Name of the tracer and of the meter
+const SCOPE: &str = "testrpc";
+
+/// Providers exporting to the collector, they are flushed on shutdown
+pub struct Otlp {
+    tracer_provider: TracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Otlp {
+    /// Build the exporters to the given endpoint, the collector is connected lazily.
+    /// The meter provider is installed globally so that `record_results` reaches it.
+    pub fn init(endpoint: &str) -> Result<Self, String> {
+        let resource = Resource::new([KeyValue::new("service.name", SCOPE)]);
+        let span_exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .with_timeout(EXPORT_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build the span exporter: {e}"))?;
+        let tracer_provider = TracerProvider::builder()
+            .with_batch_exporter(span_exporter, runtime::Tokio)
+            .with_resource(resource.clone())
+            .build();
+
+        let metric_exporter = opentelemetry_otlp::MetricExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .with_timeout(EXPORT_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build the metric exporter: {e}"))?;
+        let reader = PeriodicReader::builder(metric_exporter, runtime::Tokio)
+            .with_timeout(EXPORT_TIMEOUT)
+            .build();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(reader)
+            .with_resource(resource)
+            .build();
+        opentelemetry::global::set_meter_provider(meter_provider.clone());
+
+        Ok(Self {
+            tracer_provider,
+            meter_provider,
+        })
+    }
+
+    pub fn tracer(&self) -> Tracer {
+        self.tracer_provider.tracer(SCOPE)
+    }
+
+    /// Export what is left, an unreachable collector is only reported
+    pub fn shutdown(&self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            tracing::warn!("Failed to export the spans to the OTLP collector: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            tracing::warn!("Failed to export the metrics to the OTLP collector: {}", e);
+        }
+    }
+}
+
+/// Record the results as gauges, exported with the final snapshot on shutdown
+pub fn record_results(results: &FlowResults) {
+    let meter = opentelemetry::global::meter(SCOPE);
+    let total = &results.total;
+    for (name, value) in [
+        ("testrpc_iterations", results.total_iterations as u64),
+        ("testrpc_txs_sent", total.sent as u64),
+        ("testrpc_txs_failed", total.failed as u64),
+        ("testrpc_bytes_sent", total.bytes_sent as u64),
+        ("testrpc_bytes_failed", total.bytes_failed as u64),
+    ] {
+        meter.u64_gauge(name).build().record(value, &[]);
+    }
+    record_seconds(&meter, "testrpc_total_time", results.total_time);
+    if let Some(latency) = &total.latency {
+        record_seconds(&meter, "testrpc_latency_p50", latency.p50);
+        record_seconds(&meter, "testrpc_latency_p95", latency.p95);
+        record_seconds(&meter, "testrpc_latency_p99", latency.p99);
+    }
+}
+
+fn record_seconds(meter: &Meter, name: &'static str, value: Duration) {
+    meter
+        .f64_gauge(name)
+        .with_unit("s")
+        .build()
+        .record(value.as_secs_f64(), &[]);
+}
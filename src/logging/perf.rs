@@ -0,0 +1,138 @@
+/// Structured events of the requests for the perf tooling, written as JSON lines to a perf file
+/// and logged with target `testrpc::perf`
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    sync::OnceLock,
+    time::SystemTime,
+};
+use tracing_appender::non_blocking::{NonBlocking, NonBlockingBuilder, WorkerGuard};
+
+use super::PERF_TARGET;
+use crate::common::TestrpcError;
+
+/// Writer of the file the events are written to, see `init_perf_file`
+static PERF_FILE: OnceLock<NonBlocking> = OnceLock::new();
+
+/// Create (or truncate) the file the events are written to, one JSON line each, by a background
+/// thread so that the requests don't wait for the disk. The events are flushed when the returned
+/// guard is dropped. Only the first call has an effect.
+pub fn init_perf_file(path: &str) -> Result<WorkerGuard, TestrpcError> {
+    let file = File::create(path)
+        .map_err(|e| TestrpcError::OutputError(format!("Failed to create {path}: {e}")))?;
+    // the requests wait for the writer rather than dropping events once its buffer is full
+    let (writer, guard) = NonBlockingBuilder::default()
+        .lossy(false)
+        .finish(BufWriter::new(file));
+    let _ = PERF_FILE.set(writer);
+    Ok(guard)
+}
+
+/// Event of a request, `counter` being its request id as sent to the node
+/// (see `Adapter::sent_req_id`).
+/// The field names are relied upon by the perf tooling, see the README before renaming them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PerfEvent {
+    /// The request starts sending `txs` txs to the endpoint
+    StartSending {
+        endpoint: String,
+        counter: u64,
+        txs: usize,
+    },
+    /// The request is done, `failed` including the txs of a request failing as a whole
+    Sent {
+        endpoint: String,
+        counter: u64,
+        sent: usize,
+        failed: usize,
+    },
+}
+
+/// Line of the perf file
+#[derive(Serialize)]
+struct PerfRecord<'a> {
+    /// RFC 3339 timestamp of the event, in milliseconds
+    timestamp: String,
+    #[serde(flatten)]
+    event: &'a PerfEvent,
+}
+
+impl PerfEvent {
+    /// Write the event to the perf file and log it, nothing is done without a perf file
+    pub fn emit(&self) {
+        let Some(file) = PERF_FILE.get() else {
+            return;
+        };
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now()).to_string();
+        match self {
+            PerfEvent::StartSending {
+                endpoint,
+                counter,
+                txs,
+            } => tracing::info!(
+                target: PERF_TARGET,
+                event = "start_sending",
+                endpoint = %endpoint,
+                counter,
+                txs,
+                timestamp = %timestamp,
+                "Start sending"
+            ),
+            PerfEvent::Sent {
+                endpoint,
+                counter,
+                sent,
+                failed,
+            } => tracing::info!(
+                target: PERF_TARGET,
+                event = "sent",
+                endpoint = %endpoint,
+                counter,
+                sent,
+                failed,
+                timestamp = %timestamp,
+                "Sent"
+            ),
+        }
+        // a single write per line, the lines of concurrent requests don't interleave
+        let line = self.to_line(timestamp);
+        if let Err(e) = file.clone().write_all(line.as_bytes()) {
+            tracing::warn!("Failed to write the perf event: {}", e);
+        }
+    }
+
+    /// JSON line of the event in the perf file
+    fn to_line(&self, timestamp: String) -> String {
+        let record = PerfRecord {
+            timestamp,
+            event: self,
+        };
+        // the record only has strings and numbers, it always serializes
+        let mut line = serde_json::to_string(&record).unwrap();
+        line.push('\n');
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_line() {
+        let event = PerfEvent::Sent {
+            endpoint: "http://localhost:5000".to_string(),
+            counter: 3,
+            sent: 9,
+            failed: 1,
+        };
+        let line = event.to_line("2024-01-01T00:00:00.000Z".to_string());
+        assert_eq!(
+            line,
+            "{\"timestamp\":\"2024-01-01T00:00:00.000Z\",\"event\":\"sent\",\
+             \"endpoint\":\"http://localhost:5000\",\"counter\":3,\"sent\":9,\"failed\":1}\n"
+        );
+    }
+}
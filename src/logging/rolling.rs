@@ -0,0 +1,103 @@
+/// Log file writer rolling the file once it reaches a maximum size
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Number of rolled files kept, `<file>.1` being the most recent one
+pub const KEPT_FILES: usize = 5;
+
+/// Writes to a file which is moved to `<file>.1` once it would exceed `max_size` bytes,
+/// the previously rolled files are shifted up to `<file>.KEPT_FILES`
+pub struct SizeRollingWriter {
+    path: PathBuf,
+    max_size: u64,
+    file: File,
+    size: u64,
+}
+
+impl SizeRollingWriter {
+    /// Open the file in append mode, creating it if needed
+    pub fn new(path: impl AsRef<Path>, max_size: u64) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_size,
+            file,
+            size,
+        })
+    }
+
+    fn roll(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for i in (1..KEPT_FILES).rev() {
+            let rolled = rolled_path(&self.path, i);
+            if rolled.exists() {
+                fs::rename(&rolled, rolled_path(&self.path, i + 1))?;
+            }
+        }
+        fs::rename(&self.path, rolled_path(&self.path, 1))?;
+        self.file = open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRollingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // a single write larger than the max size still goes to one file
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.roll()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Path of the i-th rolled file
+fn rolled_path(path: &Path, i: usize) -> PathBuf {
+    let mut rolled = path.as_os_str().to_owned();
+    rolled.push(format!(".{i}"));
+    PathBuf::from(rolled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_rolling_writer() {
+        let dir = std::env::temp_dir().join(format!("testrpc-rolling-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("testrpc.log");
+
+        let mut writer = SizeRollingWriter::new(&path, 10).unwrap();
+        for line in ["first\n", "second\n", "third\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        assert_eq!(
+            fs::read_to_string(rolled_path(&path, 1)).unwrap(),
+            "second\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rolled_path(&path, 2)).unwrap(),
+            "first\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use prometheus_client::{
+    encoding::text::encode,
+    metrics::{counter::Counter, gauge::Gauge},
+    registry::Registry,
+};
+use std::sync::{Arc, OnceLock};
+use tokio::task::JoinHandle;
+
+use crate::{
+    common::{probe, RoundResults, TestrpcError},
+    ctx,
+};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Live metrics of a run, exposed in the prometheus text format
+pub struct Metrics {
+    registry: Registry,
+    sent: Counter,
+    failed: Counter,
+    iteration: Gauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the current iteration
+    pub fn set_iteration(&self, iteration: u32) {
+        self.iteration.set(iteration as i64);
+    }
+
+    /// Accumulate the results of a completed round
+    pub fn record_round(&self, results: &RoundResults) {
+        self.sent.inc_by(results.sent as u64);
+        self.failed.inc_by(results.failed as u64);
+    }
+
+    /// Encode the metrics in the prometheus text format
+    pub fn encode(&self) -> String {
+        let mut buf = String::new();
+        if let Err(e) = encode(&mut buf, &self.registry) {
+            tracing::warn!("Failed to encode metrics: {}", e);
+        }
+        buf
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let mut registry = Registry::with_prefix("testrpc");
+        let sent = Counter::default();
+        let failed = Counter::default();
+        let iteration = Gauge::default();
+        registry.register("sent", "Total number of sent transactions", sent.clone());
+        registry.register(
+            "failed",
+            "Total number of failed transactions",
+            failed.clone(),
+        );
+        registry.register("iteration", "Current iteration", iteration.clone());
+        Self {
+            registry,
+            sent,
+            failed,
+            iteration,
+        }
+    }
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// Returns the process-wide metrics, updated by the runner
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Serve the process-wide metrics on `GET /metrics` at the given address.
+/// The server runs in the background until the context is stopped.
+pub async fn serve(ctx: Arc<ctx::Context>, addr: &str) -> Result<JoinHandle<()>, TestrpcError> {
+    let handle = probe::serve(ctx, addr, "metrics", |path| {
+        (path == "/metrics")
+            .then(|| probe::response("200 OK", Some(CONTENT_TYPE), &metrics().encode()))
+    })
+    .await?;
+    tracing::info!("Serving metrics at http://{}/metrics", addr);
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let metrics = Metrics::new();
+        metrics.set_iteration(3);
+        metrics.record_round(&RoundResults {
+            sent: 5,
+            failed: 2,
+            ..Default::default()
+        });
+        let encoded = metrics.encode();
+        assert!(encoded.contains("testrpc_sent_total 5"), "{encoded}");
+        assert!(encoded.contains("testrpc_failed_total 2"), "{encoded}");
+        assert!(encoded.contains("testrpc_iteration 3"), "{encoded}");
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let ctx = Arc::new(ctx::Context::new());
+        let handle = serve(ctx.clone(), "127.0.0.1:0").await.unwrap();
+        ctx.stop(ctx::StopReason::Signal);
+        handle.await.unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+/// In-process mock of a JSON-RPC endpoint, for integration testing
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::{common::TestrpcError, jrpc};
+
+/// Maximum size of a request accepted by the mock server
+const MAX_REQUEST_SIZE: usize = 64 * 1024 * 1024;
+
+/// What the mock server received
+#[derive(Default)]
+struct Received {
+    requests: AtomicUsize,
+    txs: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+/// Mock RPC server answering every JSON-RPC request (single or batch) with a success,
+/// and recording the `send_txs` transactions it received. `status` requests are answered
+/// with the number of received transactions as height.
+/// The server stops when dropped.
+pub struct MockRpcServer {
+    addr: SocketAddr,
+    received: Arc<Received>,
+    handle: JoinHandle<()>,
+}
+
+impl MockRpcServer {
+    /// Start a server on an ephemeral port of localhost
+    pub async fn start() -> Result<Self, TestrpcError> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| TestrpcError::ServerError(format!("Failed to bind mock server: {e}")))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| TestrpcError::ServerError(e.to_string()))?;
+        let received = Arc::new(Received::default());
+        let received_cloned = received.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, received_cloned.clone()));
+            }
+        });
+        tracing::debug!("Mock RPC server listening on {}", addr);
+        Ok(Self {
+            addr,
+            received,
+            handle,
+        })
+    }
+
+    /// Url of the server
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Number of JSON-RPC requests received, each request of a batch counts
+    pub fn received_requests(&self) -> usize {
+        self.received.requests.load(Ordering::SeqCst)
+    }
+
+    /// Number of transactions received
+    pub fn received_txs(&self) -> usize {
+        self.received.txs.load(Ordering::SeqCst)
+    }
+
+    /// Number of bytes of the received transactions, once hex decoded
+    pub fn received_bytes(&self) -> usize {
+        self.received.bytes.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockRpcServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Answer a single HTTP request on the given connection
+async fn handle_connection(mut stream: TcpStream, received: Arc<Received>) {
+    let body = match read_body(&mut stream).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::debug!("Mock server failed to read request: {}", e);
+            return;
+        }
+    };
+    let response = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Array(requests)) => serde_json::to_string(
+            &requests
+                .iter()
+                .map(|request| answer(request, &received))
+                .collect::<Vec<_>>(),
+        ),
+        Ok(request) => serde_json::to_string(&answer(&request, &received)),
+        Err(e) => {
+            tracing::debug!("Mock server received an invalid request: {}", e);
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+            return;
+        }
+    };
+    let body = response.unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        tracing::debug!("Mock server failed to write response: {}", e);
+    }
+}
+
+/// Record the request and build its response
+fn answer(request: &serde_json::Value, received: &Received) -> jrpc::RpcResponse {
+    received.requests.fetch_add(1, Ordering::SeqCst);
+    if request["method"] == "send_txs" {
+        if let Some(txs) = request["params"]["txs"].as_array() {
+            received.txs.fetch_add(txs.len(), Ordering::SeqCst);
+            let bytes = txs
+                .iter()
+                .filter_map(|tx| tx.as_str())
+                .map(|tx| tx.len() / 2)
+                .sum();
+            received.bytes.fetch_add(bytes, Ordering::SeqCst);
+        }
+    }
+    // the height of the mock node is the number of txs it received
+    let result = if request["method"] == "status" {
+        serde_json::json!({ "height": received.txs.load(Ordering::SeqCst) })
+    } else {
+        serde_json::Value::Null
+    };
+    jrpc::RpcResponse::new(request["id"].as_u64().unwrap_or_default(), result)
+}
+
+/// Read the body of an HTTP request, delimited by its Content-Length
+async fn read_body(stream: &mut TcpStream) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let (headers_end, content_length) = loop {
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("connection closed before the end of the headers".to_string());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&buf[..pos]).to_lowercase();
+            let content_length = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|len| len.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            break (pos + 4, content_length);
+        }
+        if buf.len() > MAX_REQUEST_SIZE {
+            return Err("request too large".to_string());
+        }
+    };
+    if content_length > MAX_REQUEST_SIZE {
+        return Err("request too large".to_string());
+    }
+    while buf.len() < headers_end + content_length {
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("connection closed before the end of the body".to_string());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(buf[headers_end..headers_end + content_length].to_vec())
+}
@@ -0,0 +1,75 @@
+/// Live progress line of a run, rendered on stdout
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{io::IsTerminal as _, time::Duration};
+
+use crate::common::RoundResults;
+
+/// How often the progress line is refreshed
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// Progress of a run of `iterations`, a spinner if unlimited.
+    /// Returns None when stdout isn't a terminal, so that nothing is drawn in the output.
+    pub fn new(iterations: Option<usize>) -> Option<Self> {
+        if !std::io::stdout().is_terminal() {
+            return None;
+        }
+        let bar = match iterations {
+            Some(iterations) => {
+                ProgressBar::with_draw_target(Some(iterations as u64), ProgressDrawTarget::stdout())
+                    .with_style(
+                        ProgressStyle::with_template(
+                            "[{elapsed_precise}] {bar:30} {pos}/{len} iterations, {msg}",
+                        )
+                        .unwrap_or_else(|_| ProgressStyle::default_bar()),
+                    )
+            }
+            None => ProgressBar::with_draw_target(None, ProgressDrawTarget::stdout()).with_style(
+                ProgressStyle::with_template(
+                    "[{elapsed_precise}] {spinner} {pos} iterations, {msg}",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+            ),
+        };
+        Some(Self { bar })
+    }
+
+    /// Refresh the line with the results of the rounds completed so far
+    pub fn update(&self, rounds: &[RoundResults], elapsed: Duration) {
+        let sent = rounds.iter().map(|round| round.sent).sum::<usize>();
+        let failed = rounds.iter().map(|round| round.failed).sum::<usize>();
+        self.bar.set_position(rounds.len() as u64);
+        self.bar
+            .set_message(message(sent, failed, elapsed.as_secs_f64()));
+    }
+
+    /// Clear the line, before the results are printed
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+fn message(sent: usize, failed: usize, elapsed_secs: f64) -> String {
+    let rate = if elapsed_secs > 0.0 {
+        sent as f64 / elapsed_secs
+    } else {
+        0.0
+    };
+    format!("{rate:.1} tx/s, {sent} sent, {failed} failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        assert_eq!(message(100, 2, 4.0), "25.0 tx/s, 100 sent, 2 failed");
+        assert_eq!(message(0, 0, 0.0), "0.0 tx/s, 0 sent, 0 failed");
+    }
+}
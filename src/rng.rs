@@ -0,0 +1,62 @@
+/// Seed of the run, making the random choices of a run reproducible with `--seed`
+use rand::{rngs::StdRng, Rng as _, RngCore, SeedableRng as _};
+use std::sync::{Mutex, OnceLock};
+
+/// Seed of the run and the generator seeded with it
+static RNG: OnceLock<(u64, Mutex<StdRng>)> = OnceLock::new();
+
+/// Seed the random generator of the run with the given seed, or a random one.
+/// Returns the seed in use, the first one if it was already initialized.
+pub fn init(seed: Option<u64>) -> u64 {
+    let seed = seed.unwrap_or_else(|| rand::rng().random());
+    RNG.get_or_init(|| (seed, Mutex::new(StdRng::seed_from_u64(seed))))
+        .0
+}
+
+/// Seed of the run, None if `init` wasn't called
+pub fn seed() -> Option<u64> {
+    RNG.get().map(|(seed, _)| seed).copied()
+}
+
+/// Call `f` with the random generator of the run, or with the thread generator if `init`
+/// wasn't called. The draws are reproducible only as long as they happen in the same order,
+/// `for_request` is preferred for the draws of concurrent requests.
+pub fn with_rng<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+    match RNG.get() {
+        Some((_, rng)) => f(&mut *rng.lock().unwrap()),
+        None => f(&mut rand::rng()),
+    }
+}
+
+/// Generator only depending on the seed, the iteration and the request id,
+/// so that a request draws the same values whatever the scheduling of the others
+pub fn for_request(seed: u64, iteration: u32, req_id: u64) -> StdRng {
+    let mut rng_seed = [0u8; 32];
+    rng_seed[..8].copy_from_slice(&seed.to_le_bytes());
+    rng_seed[8..16].copy_from_slice(&(iteration as u64).to_le_bytes());
+    rng_seed[16..24].copy_from_slice(&req_id.to_le_bytes());
+    StdRng::from_seed(rng_seed)
+}
+
+/// Generator of the random choices of an iteration, e.g. the rpcs its round samples.
+/// Reproducible if `init` was called, random otherwise.
+pub fn for_iteration(iteration: u32) -> StdRng {
+    match seed() {
+        // the request ids start at the iteration, the last one is never used by a request
+        Some(seed) => for_request(seed, iteration, u64::MAX),
+        None => StdRng::from_rng(&mut rand::rng()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_request() {
+        let draw = |iteration, req_id| for_request(42, iteration, req_id).random::<u64>();
+        assert_eq!(draw(1, 1), draw(1, 1));
+        assert_ne!(draw(1, 1), draw(2, 1));
+        assert_ne!(draw(1, 1), draw(1, 2));
+    }
+}
@@ -1,176 +1,719 @@
-use futures::future::join_all;
+use futures::{future::join_all, Stream, StreamExt as _};
+use rand::Rng as _;
 use std::collections::HashMap;
-use std::sync::{atomic, Arc, RwLock};
+use std::sync::Arc;
+use tokio::sync::mpsc;
 use tokio::task;
 use tokio::time::Duration;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument as _;
 
 use crate::adapters::Adapter;
-use crate::common::{RoundResults, TestrpcError};
+use crate::common::{FlowResults, NodeStatus, RoundResults, TestrpcError};
 use crate::config::{self, AdapterConfig};
-use crate::{adapters, ctx};
+use crate::jrpc::DryRun;
+use crate::logging::perf::PerfEvent;
+use crate::{adapters, ctx, metrics, progress, rng};
+use serde_yaml::Value;
 
+/// Request ids reserved for each iteration, its requests and their txs taking consecutive ids
+/// from `iteration * REQ_ID_STRIDE` so that the ids of overlapping iterations don't collide
+const REQ_ID_STRIDE: u64 = 1 << 32;
+
+/// Load the endpoints declared in `rpcs` or `rpcs_file`, or from the adapter otherwise
 pub async fn load_endpoints(cfg: config::Config) -> Result<Vec<String>, TestrpcError> {
-    if let Some(rpcs) = cfg.rpcs {
-        return Ok(rpcs);
+    match (cfg.rpcs, &cfg.rpcs_file) {
+        (Some(_), Some(_)) => {
+            return Err(TestrpcError::LoadEndpointsError(
+                "rpcs and rpcs_file are mutually exclusive".to_string(),
+            ))
+        }
+        (Some(rpcs), None) => return Ok(rpcs),
+        (None, Some(rpcs_file)) => {
+            return config::read_rpcs_file(rpcs_file)
+                .map_err(|e| TestrpcError::LoadEndpointsError(e.to_string()))
+        }
+        (None, None) => {}
     }
-    let adapter = adapters::new_adapter(cfg.adapter)?;
+    let adapter = adapters::new_adapter(cfg.adapter, cfg.args.clone(), cfg.dry_run)?;
     adapter
         .load_endpoints(cfg.args.clone())
         .await
         .map_err(|e| TestrpcError::LoadEndpointsError(e.to_string()))
 }
 
+/// Check the template of each round against the adapter, see `Adapter::validate_template`
+pub fn validate_templates(cfg: &config::Config) -> Result<(), TestrpcError> {
+    let adapter = adapters::new_adapter(cfg.adapter.clone(), cfg.args.clone(), cfg.dry_run)?;
+    for (r, round) in cfg.rounds.iter().enumerate() {
+        if let Some(template) = cfg.round_template(round) {
+            adapter.validate_template(&template).map_err(|e| match e {
+                TestrpcError::LoadRoundTemplateError(msg) => {
+                    TestrpcError::LoadRoundTemplateError(format!("Round {r}: {msg}"))
+                }
+                e => e,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Ping result of an endpoint: its url, whether it is reachable and the ping latency if so
+pub type PingResult = (String, bool, Option<Duration>);
+
+/// Ping the endpoints concurrently, returning the results in the order of `rpc_urls`
 pub async fn ping_endpoints(
     adapter_cfg: AdapterConfig,
+    args: HashMap<String, Value>,
     rpc_urls: Vec<String>,
     timeout: Option<std::time::Duration>,
-) -> Result<usize, TestrpcError> {
-    let adapter = adapters::new_adapter(adapter_cfg)?;
-    let reachable_endpoints = Arc::new(atomic::AtomicUsize::new(0));
+    dry_run: Option<DryRun>,
+) -> Result<Vec<PingResult>, TestrpcError> {
+    let adapter = adapters::new_adapter(adapter_cfg, args, dry_run)?;
     let mut handles = Vec::new();
     for endpoint in rpc_urls.clone() {
         let adapter = adapter.clone();
-        let endpoint_clone = endpoint.clone();
-        let reachable_endpoints = Arc::clone(&reachable_endpoints);
         let handle = tokio::spawn(async move {
-            let res = adapter.ping_endpoint(&endpoint_clone, timeout).await;
-            tracing::info!("Pinged endpoint {}: {:?}", endpoint_clone, res);
-            if res.is_ok() {
-                reachable_endpoints.fetch_add(1, atomic::Ordering::SeqCst);
+            let start = std::time::Instant::now();
+            let res = adapter.ping_endpoint(&endpoint, timeout).await;
+            let latency = start.elapsed();
+            tracing::info!("Pinged endpoint {}: {:?}", endpoint, res);
+            match res {
+                Ok(_) => (endpoint, true, Some(latency)),
+                Err(_) => (endpoint, false, None),
             }
-            res
         });
         handles.push(handle);
     }
-    join_all(handles).await;
-    let live_count = reachable_endpoints.load(atomic::Ordering::SeqCst);
+    let results = join_all(handles)
+        .await
+        .into_iter()
+        .zip(rpc_urls.iter())
+        .map(|(res, endpoint)| res.unwrap_or_else(|_| (endpoint.clone(), false, None)))
+        .collect::<Vec<_>>();
+    let live_count = results
+        .iter()
+        .filter(|(_, reachable, _)| *reachable)
+        .count();
     tracing::info!("Pinged {} endpoints, {} live", rpc_urls.len(), live_count);
-    Ok(live_count)
+    Ok(results)
+}
+
+/// Ping the endpoints concurrently, returning the number of reachable ones
+pub async fn count_reachable_endpoints(
+    adapter_cfg: AdapterConfig,
+    args: HashMap<String, Value>,
+    rpc_urls: Vec<String>,
+    timeout: Option<std::time::Duration>,
+    dry_run: Option<DryRun>,
+) -> Result<usize, TestrpcError> {
+    let results = ping_endpoints(adapter_cfg, args, rpc_urls, timeout, dry_run).await?;
+    Ok(results
+        .iter()
+        .filter(|(_, reachable, _)| *reachable)
+        .count())
+}
+
+/// Check that at least `required` endpoints answered the ping, the run is aborted otherwise
+pub fn check_reachable(results: &[PingResult], required: usize) -> Result<(), TestrpcError> {
+    let reachable = results
+        .iter()
+        .filter(|(_, reachable, _)| *reachable)
+        .count();
+    if reachable < required {
+        return Err(TestrpcError::LoadEndpointsError(format!(
+            "{reachable} of {} endpoints are reachable, {required} required",
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Format the ping results as a table, one endpoint per line
+pub fn format_ping_results(results: &[PingResult]) -> String {
+    let width = results
+        .iter()
+        .map(|(endpoint, _, _)| endpoint.len())
+        .max()
+        .unwrap_or(0)
+        .max("ENDPOINT".len());
+    let mut table = format!("{:<width$}  {:<9}  LATENCY\n", "ENDPOINT", "REACHABLE");
+    for (endpoint, reachable, latency) in results {
+        let reachable = if *reachable { "yes" } else { "no" };
+        let latency = latency
+            .map(|latency| format!("{latency:?}"))
+            .unwrap_or_else(|| "-".to_string());
+        table.push_str(&format!("{endpoint:<width$}  {reachable:<9}  {latency}\n"));
+    }
+    table
+}
+
+/// Options of a run that are not part of the flow configuration
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Render a live progress line on stdout, if it is a terminal
+    pub progress: bool,
 }
 
 /// Run the test flow with the given configuration.
-/// This function will run the test flow until we reach cfg.iterations or if the context is stopped.
+/// This function will run the test flow until we reach cfg.iterations, cfg.max_duration elapses
+/// or if the context is stopped. Up to cfg.pipeline_depth rounds are in flight at once.
 /// Upon completion, we wait for all the open threads to complete. and the function will return a vector of RoundResults.
 pub async fn run(
     ctx: Arc<ctx::Context>,
     cfg: config::Config,
     rpc_urls: Vec<String>,
 ) -> Result<Vec<RoundResults>, TestrpcError> {
-    let mut i: u32 = 0;
-    let mut quit = ctx.recv();
-    let results = Arc::new(RwLock::new(Vec::new()));
+    run_with(ctx, cfg, rpc_urls, RunOptions::default()).await
+}
+
+/// Same as `run`, with the given options
+pub async fn run_with(
+    ctx: Arc<ctx::Context>,
+    cfg: config::Config,
+    rpc_urls: Vec<String>,
+    options: RunOptions,
+) -> Result<Vec<RoundResults>, TestrpcError> {
+    let progress = options
+        .progress
+        .then(|| progress::Progress::new(cfg.iterations))
+        .flatten();
+    let mut stream = std::pin::pin!(run_stream(ctx, cfg, rpc_urls)?);
+    let mut results = Vec::new();
+    let start = std::time::Instant::now();
+    let mut ticker = tokio::time::interval(progress::REFRESH_INTERVAL);
     loop {
-        let rounds = cfg.rounds.clone();
-        for (r, round) in rounds.into_iter().enumerate() {
-            let round_templates = cfg.round_templates.clone();
-            let rpc_urls = rpc_urls.clone();
-            let results = Arc::clone(&results);
-            i += 1;
-            let iteration = i;
-            let round_num = r;
-            let adapter = cfg.adapter.clone();
-            let timeout = cfg.timeout.map(|t| Duration::from_secs(t as u64));
-            tokio::select! {
-                _ = task::spawn(async move {
-                    match process_round(adapter, round, iteration, rpc_urls, round_templates, timeout).await {
-                        Ok(result) => {
-                            tracing::debug!("Iteration {} round {} completed", iteration, round_num);
-                            let mut results = results.write().unwrap();
-                            results.push(result);
+        tokio::select! {
+            result = stream.next() => match result {
+                Some(result) => results.push(result),
+                None => break,
+            },
+            _ = ticker.tick(), if progress.is_some() => {
+                if let Some(progress) = &progress {
+                    progress.update(&results, start.elapsed());
+                }
+            }
+        }
+    }
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    Ok(results)
+}
+
+/// Run the test flow like `run`, yielding the results of each round as soon as it completes
+/// (warmup rounds and failed rounds yield nothing). The stream ends with the flow.
+pub fn run_stream(
+    ctx: Arc<ctx::Context>,
+    mut cfg: config::Config,
+    rpc_urls: Vec<String>,
+) -> Result<impl Stream<Item = RoundResults>, TestrpcError> {
+    if cfg.rounds.is_empty() {
+        // the flow would loop over no rounds forever
+        return Err(TestrpcError::LoadConfigError(
+            "no rounds declared".to_string(),
+            String::new(),
+        ));
+    }
+    cfg.expand_to_all(rpc_urls.len());
+    let adapter = adapters::new_adapter(cfg.adapter.clone(), cfg.args.clone(), cfg.dry_run)?;
+    let templates = cfg
+        .rounds
+        .iter()
+        .enumerate()
+        .map(|(r, round)| {
+            cfg.round_template(round)
+                .ok_or(TestrpcError::LoadRoundTemplateError(format!(
+                    "No template found for round {r}"
+                )))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (results, rx) = mpsc::unbounded_channel();
+    let mut quit = ctx.recv();
+    task::spawn(async move {
+        let mut i: u32 = 0;
+        let deadline = cfg.max_duration.map(|max_duration| {
+            let ctx = ctx.clone();
+            task::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(max_duration)).await;
+                tracing::info!("Reached max duration of {}s", max_duration);
+                ctx.stop(ctx::StopReason::Deadline);
+            })
+        });
+        let depth = cfg.pipeline_depth.unwrap_or(1).max(1);
+        let mut pipeline = Pipeline {
+            start: std::time::Instant::now(),
+            rounds: Vec::new(),
+            load: 1.0,
+            adaptive: cfg.adaptive.clone(),
+            grace: cfg.shutdown_grace.unwrap_or(Duration::MAX),
+            warmup: cfg.warmup.unwrap_or(0),
+            results: results.clone(),
+            rounds_per_pass: cfg.rounds.len(),
+            passes: HashMap::new(),
+        };
+        let mut pass: u32 = 0;
+        'flow: loop {
+            pass += 1;
+            let rounds = cfg.rounds.clone();
+            for (r, round) in rounds.into_iter().enumerate() {
+                let mut template = templates[r].clone();
+                let rpc_urls = rpc_urls.clone();
+                let round_results = results.clone();
+                i += 1;
+                let iteration = i;
+                let round = round.sampled(iteration);
+                metrics::metrics().set_iteration(iteration);
+                let round_num = r;
+                let adapter = adapter.clone();
+                let timeout = cfg.timeout.map(|t| Duration::from_secs(t as u64));
+                let txs = cfg.ramp.as_ref().map(|ramp| ramp.txs(iteration));
+                let txs = match &cfg.adaptive {
+                    Some(adaptive) => {
+                        Some(adaptive.txs(txs.unwrap_or(template.txs), pipeline.load))
+                    }
+                    None => txs,
+                };
+                if let Some(txs) = txs {
+                    template.txs = txs;
+                }
+                let round_load = cfg.adaptive.is_some().then_some(pipeline.load);
+                let warmup = cfg.warmup.unwrap_or(0);
+                if warmup > 0 && iteration as usize == warmup + 1 {
+                    tracing::info!("Warmup of {} iterations done, starting measurement", warmup);
+                }
+                let span = tracing::info_span!("round", iteration, round_num);
+                let cancel = ctx.cancellation_token();
+                let aborted = aborted_round_results(&round, &template, iteration);
+                let status_urls = cfg.collect_status.then(|| {
+                    round
+                        .rpcs
+                        .iter()
+                        .filter_map(|rpc| rpc_urls.get(*rpc).cloned())
+                        .collect::<Vec<_>>()
+                });
+                let flow_start = pipeline.start;
+                let handle = task::spawn(async move {
+                    let outcome = process_round(
+                        adapter.clone(),
+                        round,
+                        iteration,
+                        rpc_urls,
+                        template,
+                        timeout,
+                        cancel,
+                    )
+                    .instrument(span)
+                    .await;
+                    let outcome = match (outcome, status_urls) {
+                        (Ok(mut result), Some(status_urls)) => {
+                            result.node_status =
+                                collect_node_status(adapter.as_ref(), status_urls, timeout).await;
+                            Ok(result)
+                        }
+                        (outcome, _) => outcome,
+                    };
+                    let outcome = outcome.map(|mut result| {
+                        result.load = round_load;
+                        result
+                    });
+                    // a round failing as a whole counts as failing all its txs
+                    let failure_ratio = outcome.as_ref().map_or(1.0, RoundResults::failure_ratio);
+                    match outcome {
+                        Ok(result) if iteration as usize <= warmup => {
+                            tracing::info!(
+                                "Warmup iteration {} round {} completed, results excluded",
+                                iteration,
+                                round_num
+                            );
+                            tracing::debug!("Warmup results: {:?}", result);
+                        }
+                        Ok(mut result) => {
+                            result.elapsed = Some(flow_start.elapsed());
+                            tracing::debug!(
+                                "Iteration {} round {} completed at {:.1} tx/s",
+                                iteration,
+                                round_num,
+                                result.tx_rate.unwrap_or_default()
+                            );
+                            metrics::metrics().record_round(&result);
+                            let _ = round_results.send(result);
                         }
                         Err(e) => {
-                            tracing::warn!("Iteration {} round {} failed: {}", iteration, round_num, e);
+                            tracing::warn!(
+                                "Iteration {} round {} failed: {}",
+                                iteration,
+                                round_num,
+                                e
+                            );
+                        }
+                    }
+                    failure_ratio
+                });
+                pipeline.rounds.push(InFlightRound {
+                    iteration,
+                    round_num,
+                    pass,
+                    aborted,
+                    handle,
+                });
+                // the next round is launched once a slot of the pipeline is free,
+                // only after this one completes without pipelining
+                if pipeline.wait(depth - 1, &mut quit).await {
+                    break 'flow;
+                }
+                tokio::select! {
+                    reason = quit.recv() => {
+                        tracing::debug!("ctx stopped ({}) during iteration {} round {}", reason, iteration, round_num);
+                        pipeline.drain().await;
+                        break 'flow;
+                    }
+                    _ = tokio::time::sleep(cfg.next_interval()) => {}
+                }
+                if let Some(iterations) = cfg.iterations {
+                    if i >= iterations as u32 {
+                        tracing::debug!("Reached max iterations: {}", i);
+                        if !pipeline.wait(0, &mut quit).await {
+                            ctx.stop(ctx::StopReason::MaxIterations);
                         }
+                        break 'flow;
                     }
-                }) => {}
-                _ = quit.recv() => {
-                    tracing::debug!("Iteration {} round {} timed out as ctx was stopped", iteration, round_num);
-                    break;
                 }
             }
+        }
+        if let Some(deadline) = deadline {
+            deadline.abort();
+        }
+    });
+    Ok(UnboundedReceiverStream::new(rx))
+}
+
+/// Round launched by `run_stream`, not awaited yet
+struct InFlightRound {
+    iteration: u32,
+    round_num: usize,
+    /// Number of the pass over `Config.rounds` the round belongs to, starting at 1
+    pass: u32,
+    /// Results of the round if it's aborted
+    aborted: RoundResults,
+    /// Returns the failure ratio of the round
+    handle: task::JoinHandle<f64>,
+}
+
+/// Rounds in flight, up to `Config.pipeline_depth` of them
+struct Pipeline {
+    /// Start of the flow
+    start: std::time::Instant,
+    /// In the order they were launched
+    rounds: Vec<InFlightRound>,
+    /// Fraction of the configured txs sent, lowered by the adaptive load on failures
+    load: f64,
+    adaptive: Option<config::AdaptiveConfig>,
+    grace: Duration,
+    warmup: usize,
+    results: mpsc::UnboundedSender<RoundResults>,
+    /// Number of rounds of a pass over `Config.rounds`
+    rounds_per_pass: usize,
+    /// Failures of the passes whose rounds didn't all complete yet, by pass
+    passes: HashMap<u32, PassFailures>,
+}
+
+/// Failures of the completed rounds of a pass over `Config.rounds`
+#[derive(Default)]
+struct PassFailures {
+    rounds: usize,
+    /// Txs of the rounds weighted by their failure ratio
+    failed: f64,
+    txs: f64,
+}
+
+impl Pipeline {
+    /// Wait for the rounds to complete until at most `limit` are in flight, updating the load.
+    /// Returns true if the ctx was stopped meanwhile, the rounds are drained then.
+    async fn wait(&mut self, limit: usize, quit: &mut ctx::StopReceiver) -> bool {
+        while self.rounds.len() > limit {
+            let handles = self.rounds.iter_mut().map(|round| &mut round.handle);
             tokio::select! {
-                _ = quit.recv() => {
-                    tracing::debug!("ctx stopped during iteration {} round {}", iteration, round_num);
-                    break;
+                (res, index, _) = futures::future::select_all(handles) => {
+                    let round = self.rounds.remove(index);
+                    match res {
+                        Ok(failure_ratio) => self.update_load(&round, failure_ratio),
+                        Err(e) => {
+                            tracing::warn!("Iteration {} round {} panicked: {}", round.iteration, round.round_num, e);
+                            self.update_load(&round, 1.0);
+                        }
+                    }
                 }
-                _ = tokio::time::sleep(Duration::from_secs(cfg.interval)) => {}
-            }
-            if let Some(iterations) = cfg.iterations {
-                if i >= iterations as u32 {
-                    tracing::debug!("Reached max iterations: {}", i);
-                    break;
+                reason = quit.recv() => {
+                    // Stop launching new rounds, but drain the in-flight ones to keep their results
+                    tracing::debug!("ctx stopped ({}), draining {} in-flight rounds", reason, self.rounds.len());
+                    self.drain().await;
+                    return true;
                 }
             }
         }
-        if let Some(iterations) = cfg.iterations {
-            if i >= iterations as u32 {
-                tracing::debug!("Reached max iterations: {}", i);
-                break;
+        false
+    }
+
+    /// Record the failures of a completed round. Once all the rounds of its pass over
+    /// `Config.rounds` completed, adapt the load of the next rounds to the failures of the pass.
+    fn update_load(&mut self, round: &InFlightRound, failure_ratio: f64) {
+        let Some(adaptive) = &self.adaptive else {
+            return;
+        };
+        let txs = round.aborted.failed as f64;
+        let failures = self.passes.entry(round.pass).or_default();
+        failures.rounds += 1;
+        failures.failed += failure_ratio * txs;
+        failures.txs += txs;
+        if failures.rounds < self.rounds_per_pass {
+            return;
+        }
+        let failures = self.passes.remove(&round.pass).unwrap_or_default();
+        let failure_ratio = match failures.txs {
+            txs if txs > 0.0 => failures.failed / txs,
+            _ => 0.0,
+        };
+        let next_load = adaptive.next_load(self.load, failure_ratio);
+        if next_load < self.load {
+            tracing::warn!(
+                "Pass {} over the rounds failed {:.1}% of its txs, backing off to {:.1}% of the load",
+                round.pass,
+                failure_ratio * 100.0,
+                next_load * 100.0
+            );
+        }
+        self.load = next_load;
+    }
+
+    /// Wait for the in-flight rounds once the flow is stopped, the ones still running
+    /// after the grace period are aborted and their txs counted as failed
+    async fn drain(&mut self) {
+        let start = std::time::Instant::now();
+        for mut round in std::mem::take(&mut self.rounds) {
+            let remaining = self.grace.saturating_sub(start.elapsed());
+            match tokio::time::timeout(remaining, &mut round.handle).await {
+                Ok(Err(e)) => {
+                    tracing::warn!(
+                        "Iteration {} round {} panicked: {}",
+                        round.iteration,
+                        round.round_num,
+                        e
+                    );
+                }
+                Ok(Ok(_)) => {}
+                Err(_) => {
+                    tracing::warn!(
+                        "Iteration {} round {} still running after {:?}, aborting it",
+                        round.iteration,
+                        round.round_num,
+                        self.grace
+                    );
+                    round.handle.abort();
+                    let _ = round.handle.await;
+                    if round.iteration as usize > self.warmup {
+                        round.aborted.elapsed = Some(self.start.elapsed());
+                        metrics::metrics().record_round(&round.aborted);
+                        let _ = self.results.send(round.aborted);
+                    }
+                }
             }
         }
     }
-    let results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
+}
+
+/// Results of a round aborted before completion, all its txs are counted as failed
+fn aborted_round_results(
+    round: &config::Round,
+    template: &config::RoundTemplate,
+    iteration: u32,
+) -> RoundResults {
+    let failed = (0..round.rpcs.len())
+        .map(|i| template.txs * round.weight(i) as usize)
+        .sum::<usize>();
+    RoundResults {
+        iteration: Some(iteration),
+        failed,
+        bytes_failed: failed * template.mean_tx_size(),
+        ..Default::default()
+    }
+}
+
+/// Aborts the tasks when dropped, so that the sends of an aborted round don't outlive it
+#[derive(Default)]
+struct AbortOnDrop(Vec<task::AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for handle in &self.0 {
+            handle.abort();
+        }
+    }
+}
+
+/// Fetch the status of the given nodes concurrently, the nodes whose status can't be fetched are left out
+async fn collect_node_status(
+    adapter: &impl Adapter,
+    rpc_urls: Vec<String>,
+    timeout: Option<Duration>,
+) -> HashMap<String, NodeStatus> {
+    let statuses = join_all(
+        rpc_urls
+            .iter()
+            .map(|rpc_url| adapter.node_status(rpc_url, timeout)),
+    )
+    .await;
+    rpc_urls
+        .into_iter()
+        .zip(statuses)
+        .filter_map(|(rpc_url, status)| match status {
+            Ok(status) => Some((rpc_url, status)),
+            Err(TestrpcError::Unsupported(e)) => {
+                tracing::debug!("Not fetching the status of {}: {}", rpc_url, e);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch the status of {}: {}", rpc_url, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Run the whole test flow once with the given configuration: load the endpoints,
+/// run the rounds until cfg.iterations is reached and return the results of the flow.
+/// Requires cfg.iterations or cfg.max_duration to be set, as there is no way to stop the flow otherwise.
+/// The config is validated first, see `config::validate` and `validate_templates`.
+pub async fn run_once(cfg: config::Config) -> Result<FlowResults, TestrpcError> {
+    if cfg.iterations.is_none() && cfg.max_duration.is_none() {
+        return Err(TestrpcError::MissingArgs(
+            "iterations or max_duration".to_string(),
+        ));
+    }
+    config::validate(&cfg)?;
+    validate_templates(&cfg)?;
+    let start = std::time::Instant::now();
+    let ctx = Arc::new(ctx::Context::new());
+    let rpc_urls = load_endpoints(cfg.clone()).await?;
+    let ramp = cfg.ramp.clone();
+    let round_results = run(ctx.clone(), cfg, rpc_urls).await?;
+    let mut results = FlowResults::new_from_round_results(round_results, start.elapsed());
+    results.stop_reason = ctx.stop_reason();
+    if let Some(ramp) = ramp {
+        results.set_ramp_breaking_point(&ramp);
+    }
     Ok(results)
 }
 
-/// Process a single round, sending transactions to the RPC servers concurrently
+/// Process a single round, sending transactions to the RPC servers concurrently,
+/// or one after the other if the round isn't concurrent.
+/// If `txs` is set, it overrides the number of txs of the round template.
 async fn process_round(
-    cfg: AdapterConfig,
+    adapter: Arc<impl Adapter + Send + Sync + 'static>,
     round: config::Round,
     iteration: u32,
     rpc_urls: Vec<String>,
-    round_templates: HashMap<String, config::RoundTemplate>,
+    mut round_template: config::RoundTemplate,
     timeout: Option<std::time::Duration>,
+    cancel: CancellationToken,
 ) -> Result<RoundResults, TestrpcError> {
-    let mut req_id = iteration as u64;
-    let mut results = RoundResults { sent: 0, failed: 0 };
+    if round.broadcast_identical {
+        round_template.broadcast_seed = Some(rng::for_iteration(iteration).random());
+        round_template.broadcast_requests = round.rpcs.len();
+    }
+    let mut results = RoundResults {
+        iteration: Some(iteration),
+        ..Default::default()
+    };
     let mut handles = Vec::new();
+    let mut outcomes = Vec::new();
+    let mut abort_handles = AbortOnDrop::default();
+    let start = std::time::Instant::now();
 
-    let adapter = adapters::new_adapter(cfg)?;
+    if let Some(rpc) = round.rpcs.iter().find(|rpc| rpc_urls.len() <= **rpc) {
+        return Err(TestrpcError::LoadEndpointsError(format!(
+            "RPC index out of bounds: {rpc}"
+        )));
+    }
 
-    for rpc in &round.rpcs {
-        if rpc_urls.len() <= *rpc {
-            return Err(TestrpcError::LoadEndpointsError(format!(
-                "RPC index out of bounds: {rpc}"
-            )));
-        }
+    let mut next_req_id = u64::from(iteration).wrapping_mul(REQ_ID_STRIDE);
+    for (i, rpc) in round.rpcs.iter().enumerate() {
         let rpc_url = rpc_urls[*rpc].clone();
-        let req_id_clone = req_id;
 
-        let template = round.get_template(round_templates.clone()).ok_or(
-            TestrpcError::LoadRoundTemplateError("No template found".to_string()),
-        )?;
+        let mut template = round_template.clone();
+        template.txs *= round.weight(i) as usize;
+        // the requests sending their txs one by one (or in bursts) use one id per tx
+        let req_id = next_req_id;
+        next_req_id = next_req_id.wrapping_add(template.txs.max(1) as u64);
+        // counted as failed if the send fails as a whole
+        let failed = RoundResults {
+            failed: template.txs,
+            bytes_failed: template.txs * template.mean_tx_size(),
+            ..Default::default()
+        };
 
+        let sent_req_id = adapter.sent_req_id(req_id);
         let adapter = adapter.clone();
-        let handle = tokio::spawn(async move {
-            adapter
-                .send_txs(
-                    &rpc_url,
-                    req_id_clone,
-                    iteration,
-                    template.txs,
-                    template.tx_size,
-                    timeout,
-                )
-                .await
-        });
+        let cancel = cancel.clone();
+        // child of the round span, the spawned task doesn't inherit it otherwise.
+        // The request id is the one sent to the node, to correlate the span with its logs.
+        let span = tracing::info_span!("send_txs", rpc_url = %rpc_url, req_id = sent_req_id);
+        let handle = tokio::spawn(
+            async move {
+                PerfEvent::StartSending {
+                    endpoint: rpc_url.clone(),
+                    counter: sent_req_id,
+                    txs: template.txs,
+                }
+                .emit();
+                let result = adapter
+                    .send_txs(&rpc_url, req_id, iteration, &template, timeout, &cancel)
+                    .await;
+                let (sent, failed) = match &result {
+                    Ok(results) => (results.sent, results.failed),
+                    Err(_) => (0, template.txs),
+                };
+                PerfEvent::Sent {
+                    endpoint: rpc_url,
+                    counter: sent_req_id,
+                    sent,
+                    failed,
+                }
+                .emit();
+                result
+            }
+            .instrument(span),
+        );
+        abort_handles.0.push(handle.abort_handle());
 
-        handles.push(handle);
-        req_id += 1;
+        if round.is_concurrent() {
+            handles.push(((rpc_urls[*rpc].clone(), failed), handle));
+        } else {
+            outcomes.push(((rpc_urls[*rpc].clone(), failed), handle.await));
+        }
     }
 
-    let results_vec = join_all(handles).await;
+    let (rpcs, handles): (Vec<_>, Vec<_>) = handles.into_iter().unzip();
+    outcomes.extend(rpcs.into_iter().zip(join_all(handles).await));
 
-    for result in results_vec {
-        match result {
-            Ok(Ok(round_results)) => {
-                results.sent += round_results.sent;
-                results.failed += round_results.failed;
+    // the sends failing as a whole count as failed, the round keeps the results of the others
+    for ((rpc_url, failed), result) in outcomes {
+        let node_results = match result {
+            Ok(Ok(node_results)) => node_results,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to send {} txs to {}: {}", failed.failed, rpc_url, e);
+                failed
             }
-            Ok(Err(e)) => return Err(e),
-            Err(e) => return Err(TestrpcError::ExecutionError(e.to_string())),
-        }
+            Err(e) => {
+                tracing::warn!("Send task to {} failed: {}", rpc_url, e);
+                failed
+            }
+        };
+        results.merge_node(&rpc_url, &node_results);
     }
+    results.compute_latency_stats();
+    results.set_duration(start.elapsed());
     Ok(results)
 }
 
@@ -181,33 +724,650 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
 
+    #[tokio::test]
+    async fn test_run_once() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 3
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+  - http://localhost:5001
+rounds:
+  - rpcs: [0, 1]
+    template:
+      txs: 2
+      tx_size: 10
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let results = run_once(cfg).await.unwrap();
+        assert_eq!(results.total_iterations, 3);
+        assert_eq!(results.total.sent, 12);
+        assert_eq!(results.total.failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_stream() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 3
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 2
+      tx_size: 10
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let rpc_urls = cfg.rpcs.clone().unwrap();
+        let stream = run_stream(Arc::new(ctx::Context::new()), cfg, rpc_urls).unwrap();
+        let iterations = stream
+            .map(|result| result.iteration)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(iterations, vec![Some(1), Some(2), Some(3)]);
+    }
+
+    #[tokio::test]
+    async fn test_run_once_max_duration() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+max_duration: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 1
+      tx_size: 10
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let start = std::time::Instant::now();
+        let results = run_once(cfg).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(results.total_iterations > 0);
+        assert_eq!(results.stop_reason, Some(ctx::StopReason::Deadline));
+    }
+
+    #[tokio::test]
+    async fn test_run_once_ramp() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 3
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+ramp:
+  start_txs: 1
+  step: 2
+  max_txs: 4
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 100
+      tx_size: 10
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let results = run_once(cfg).await.unwrap();
+        let sent = results.rounds.iter().map(|r| r.sent).collect::<Vec<_>>();
+        assert_eq!(sent, vec![1, 3, 4]);
+        assert_eq!(results.ramp_breaking_point, None);
+    }
+
+    #[tokio::test]
+    async fn test_run_warmup() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 3
+warmup: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 1
+      tx_size: 10
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let results = run_once(cfg).await.unwrap();
+        assert_eq!(results.total_iterations, 2);
+        let iterations = results
+            .rounds
+            .iter()
+            .map(|r| r.iteration)
+            .collect::<Vec<_>>();
+        assert_eq!(iterations, vec![Some(2), Some(3)]);
+    }
+
+    #[tokio::test]
+    async fn test_run_drains_on_stop() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 4
+      tx_size: 10
+      latency: 50ms
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let ctx = Arc::new(ctx::Context::new());
+        let ctx_cloned = ctx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            ctx_cloned.stop(ctx::StopReason::Signal);
+        });
+        let rpc_urls = vec!["http://localhost:5000".to_string()];
+        let results = run(ctx.clone(), cfg, rpc_urls).await.unwrap();
+        assert_eq!(ctx.stop_reason(), Some(ctx::StopReason::Signal));
+        // the round in-flight when stopping is recorded, with the txs sent before the stop
+        assert_eq!(results.len(), 1);
+        assert!(results[0].sent > 0 && results[0].sent < 4);
+        assert_eq!(results[0].failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_pipelined() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 4
+pipeline_depth: 4
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 2
+      tx_size: 10
+      latency: 200ms
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let start = std::time::Instant::now();
+        let results = run_once(cfg).await.unwrap();
+        // the rounds overlap, one after the other would take 800ms
+        assert!(start.elapsed() < Duration::from_millis(600));
+        assert_eq!(results.total_iterations, 4);
+        assert_eq!(results.total.sent, 8);
+    }
+
+    #[tokio::test]
+    async fn test_run_stream_no_rounds() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 1
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds: []
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        let rpc_urls = cfg.rpcs.clone().unwrap();
+        let err = run_stream(Arc::new(ctx::Context::new()), cfg, rpc_urls)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("no rounds"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_run_once_invalid_config() {
+        let cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 1
+pipeline_depth: 0
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+"#,
+        )
+        .unwrap();
+        let err = run_once(cfg).await.unwrap_err();
+        assert!(err.to_string().contains("pipeline_depth"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_run_pipelined_out_of_order() {
+        let mut cfg = config::parse_config_yaml(
+            r#"
+interval: 0
+back_to_back: true
+iterations: 4
+pipeline_depth: 4
+adapter: hotshot
+args: {}
+round_templates: {}
+rpcs:
+  - http://localhost:5000
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 3
+      tx_size: 10
+      latency: 150ms
+  - rpcs: [0]
+    template:
+      txs: 1
+      tx_size: 10
+"#,
+        )
+        .unwrap();
+        cfg.dry_run = Some(DryRun::Noop);
+        // the short rounds 2 and 4 complete before the long rounds 1 and 3
+        let results = run_once(cfg).await.unwrap();
+        let iterations = results
+            .rounds
+            .iter()
+            .map(|round| round.iteration)
+            .collect::<Vec<_>>();
+        assert_eq!(iterations, vec![Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(results.rounds[0].sent, 3);
+        assert_eq!(results.rounds[1].sent, 1);
+    }
+
+    #[test]
+    fn test_check_reachable() {
+        let results = [
+            ("http://localhost:5000".to_string(), true, None),
+            ("http://localhost:5001".to_string(), false, None),
+        ];
+        assert!(check_reachable(&results, 1).is_ok());
+        let err = check_reachable(&results, 2).unwrap_err();
+        assert!(err.to_string().contains("1 of 2 endpoints"), "{err}");
+    }
+
+    #[test]
+    fn test_format_ping_results() {
+        let table = format_ping_results(&[
+            (
+                "http://localhost:5000".to_string(),
+                true,
+                Some(Duration::from_millis(3)),
+            ),
+            ("http://localhost:5001".to_string(), false, None),
+        ]);
+        assert_eq!(
+            table,
+            "ENDPOINT               REACHABLE  LATENCY\n\
+             http://localhost:5000  yes        3ms\n\
+             http://localhost:5001  no         -\n"
+        );
+    }
+
     #[tokio::test]
     async fn test_process_round() {
-        // set DRY_RUN to avoid sending requests
-        std::env::set_var("DRY_RUN", "true");
         let round = Round {
             rpcs: vec![0],
+            to_all: false,
+            sample: None,
+            broadcast_identical: false,
             repeat: Some(1),
             template: Some(RoundTemplate {
                 txs: 1,
                 tx_size: 1,
-                latency: None,
+                ..Default::default()
             }),
             use_template: None,
+            weights: None,
+            concurrent: None,
         };
         let rpc_urls = vec!["http://localhost:5000".to_string()];
-        let round_templates = HashMap::new();
-        let results = process_round(
+        let template = round.template.clone().unwrap();
+        let adapter = adapters::new_adapter(
             config::AdapterConfig::Hotshot,
+            HashMap::new(),
+            Some(DryRun::Noop),
+        )
+        .unwrap();
+        let results = process_round(
+            adapter,
             round,
             0,
             rpc_urls,
-            round_templates,
+            template,
             Some(std::time::Duration::from_secs(5)),
+            CancellationToken::new(),
         )
         .await
         .unwrap();
         assert_eq!(results.sent, 1);
         assert_eq!(results.failed, 0);
+        assert_eq!(results.bytes_sent, 1);
+        assert_eq!(results.bytes_failed, 0);
+        assert_eq!(results.latencies.len(), 1);
+        assert!(results.latency.is_some());
+    }
+
+    /// Adapter failing the sends to the endpoints containing "bad"
+    struct PartialAdapter;
+
+    impl Adapter for PartialAdapter {
+        async fn load_endpoints(
+            &self,
+            _args: HashMap<String, serde_yaml::Value>,
+        ) -> Result<Vec<String>, TestrpcError> {
+            Ok(Vec::new())
+        }
+
+        async fn ping_endpoint(
+            &self,
+            _rpc_url: &str,
+            _timeout: Option<std::time::Duration>,
+        ) -> Result<bool, TestrpcError> {
+            Ok(true)
+        }
+
+        async fn send_txs(
+            &self,
+            rpc_url: &str,
+            _req_id: u64,
+            _iteration: u32,
+            template: &RoundTemplate,
+            _timeout: Option<std::time::Duration>,
+            _cancel: &CancellationToken,
+        ) -> Result<RoundResults, TestrpcError> {
+            if rpc_url.contains("bad") {
+                return Err(TestrpcError::RequestError("unreachable".to_string()));
+            }
+            Ok(RoundResults {
+                sent: template.txs,
+                bytes_sent: template.txs * template.tx_size,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_round_partial() {
+        let round = Round {
+            rpcs: vec![0, 1],
+            to_all: false,
+            sample: None,
+            broadcast_identical: false,
+            repeat: None,
+            template: Some(RoundTemplate {
+                txs: 2,
+                tx_size: 10,
+                ..Default::default()
+            }),
+            use_template: None,
+            weights: None,
+            concurrent: None,
+        };
+        let rpc_urls = vec![
+            "http://good:5000".to_string(),
+            "http://bad:5000".to_string(),
+        ];
+        let template = round.template.clone().unwrap();
+        let results = process_round(
+            Arc::new(PartialAdapter),
+            round.clone(),
+            0,
+            rpc_urls.clone(),
+            template.clone(),
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        // the failed send doesn't discard the successful one
+        assert_eq!((results.sent, results.failed), (2, 2));
+        assert_eq!((results.bytes_sent, results.bytes_failed), (20, 20));
+        assert_eq!(results.per_node["http://bad:5000"].failed, 2);
+
+        // an unknown rpc is a setup error
+        let round = Round {
+            rpcs: vec![0, 2],
+            ..round
+        };
+        assert!(process_round(
+            Arc::new(PartialAdapter),
+            round,
+            0,
+            rpc_urls,
+            template,
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .is_err());
+    }
+
+    /// Adapter recording the request ids of the sends
+    #[derive(Default)]
+    struct RecordingAdapter {
+        req_ids: std::sync::Mutex<Vec<u64>>,
+    }
+
+    impl Adapter for RecordingAdapter {
+        async fn load_endpoints(
+            &self,
+            _args: HashMap<String, serde_yaml::Value>,
+        ) -> Result<Vec<String>, TestrpcError> {
+            Ok(Vec::new())
+        }
+
+        async fn ping_endpoint(
+            &self,
+            _rpc_url: &str,
+            _timeout: Option<std::time::Duration>,
+        ) -> Result<bool, TestrpcError> {
+            Ok(true)
+        }
+
+        async fn send_txs(
+            &self,
+            _rpc_url: &str,
+            req_id: u64,
+            _iteration: u32,
+            template: &RoundTemplate,
+            _timeout: Option<std::time::Duration>,
+            _cancel: &CancellationToken,
+        ) -> Result<RoundResults, TestrpcError> {
+            self.req_ids.lock().unwrap().push(req_id);
+            Ok(RoundResults {
+                sent: template.txs,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_round_req_ids() {
+        let round = Round {
+            rpcs: vec![0, 1],
+            to_all: false,
+            sample: None,
+            broadcast_identical: false,
+            repeat: None,
+            template: Some(RoundTemplate {
+                txs: 3,
+                tx_size: 1,
+                ..Default::default()
+            }),
+            use_template: None,
+            weights: None,
+            concurrent: Some(false),
+        };
+        let rpc_urls = vec![
+            "http://localhost:5000".to_string(),
+            "http://localhost:5001".to_string(),
+        ];
+        let adapter = Arc::new(RecordingAdapter::default());
+        for iteration in [1, 2] {
+            process_round(
+                adapter.clone(),
+                round.clone(),
+                iteration,
+                rpc_urls.clone(),
+                round.template.clone().unwrap(),
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        }
+        // each request leaves an id per tx, the iterations don't overlap
+        assert_eq!(
+            *adapter.req_ids.lock().unwrap(),
+            vec![
+                REQ_ID_STRIDE,
+                REQ_ID_STRIDE + 3,
+                2 * REQ_ID_STRIDE,
+                2 * REQ_ID_STRIDE + 3
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_round_weights() {
+        let round = Round {
+            rpcs: vec![0, 1],
+            to_all: false,
+            sample: None,
+            broadcast_identical: false,
+            repeat: None,
+            template: Some(RoundTemplate {
+                txs: 2,
+                tx_size: 1,
+                ..Default::default()
+            }),
+            use_template: None,
+            weights: Some(vec![1, 3]),
+            concurrent: None,
+        };
+        let rpc_urls = vec![
+            "http://localhost:5000".to_string(),
+            "http://localhost:5001".to_string(),
+        ];
+        let adapter = adapters::new_adapter(
+            config::AdapterConfig::Hotshot,
+            HashMap::new(),
+            Some(DryRun::Noop),
+        )
+        .unwrap();
+        let template = round.template.clone().unwrap();
+        let results = process_round(
+            adapter,
+            round,
+            0,
+            rpc_urls,
+            template,
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.sent, 8);
+        assert_eq!(results.per_node["http://localhost:5000"].sent, 2);
+        assert_eq!(results.per_node["http://localhost:5001"].sent, 6);
+    }
+
+    #[tokio::test]
+    async fn test_process_round_sequential() {
+        let round = Round {
+            rpcs: vec![0, 1, 2, 3],
+            to_all: false,
+            sample: None,
+            broadcast_identical: false,
+            repeat: None,
+            template: Some(RoundTemplate {
+                txs: 1,
+                tx_size: 1,
+                ..Default::default()
+            }),
+            use_template: None,
+            weights: None,
+            concurrent: Some(false),
+        };
+        let rpc_urls = (0..4)
+            .map(|i| format!("http://localhost:500{i}"))
+            .collect::<Vec<_>>();
+        let adapter = adapters::new_adapter(
+            config::AdapterConfig::Hotshot,
+            HashMap::new(),
+            // each noop request takes 5ms
+            Some(DryRun::Noop),
+        )
+        .unwrap();
+        let template = round.template.clone().unwrap();
+        let start = std::time::Instant::now();
+        let results = process_round(
+            adapter,
+            round,
+            0,
+            rpc_urls,
+            template,
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert!(start.elapsed() >= std::time::Duration::from_millis(20));
+        assert_eq!(results.sent, 4);
+        assert_eq!(results.per_node.len(), 4);
+        // the duration of the round covers the sequential sends
+        assert!(results.duration.unwrap() >= std::time::Duration::from_millis(20));
+        assert!(results.tx_rate.unwrap() <= 4.0 / 0.02);
     }
 }
@@ -3,6 +3,8 @@ use tracing::debug;
 
 use crate::common::TestrpcError;
 
+/// Wait for SIGTERM, SIGINT or SIGQUIT
+#[cfg(unix)]
 pub async fn wait_exit_signals() -> Result<(), TestrpcError> {
     let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
         .map_err(|e| TestrpcError::TerminationError(e.to_string()))?;
@@ -25,3 +27,43 @@ pub async fn wait_exit_signals() -> Result<(), TestrpcError> {
 
     Ok(())
 }
+
+/// Wait for Ctrl-C, Ctrl-Break, or the console being closed or the system shutting down
+#[cfg(windows)]
+pub async fn wait_exit_signals() -> Result<(), TestrpcError> {
+    let mut ctrl_c =
+        signal::windows::ctrl_c().map_err(|e| TestrpcError::TerminationError(e.to_string()))?;
+    let mut ctrl_break =
+        signal::windows::ctrl_break().map_err(|e| TestrpcError::TerminationError(e.to_string()))?;
+    let mut ctrl_close =
+        signal::windows::ctrl_close().map_err(|e| TestrpcError::TerminationError(e.to_string()))?;
+    let mut ctrl_shutdown = signal::windows::ctrl_shutdown()
+        .map_err(|e| TestrpcError::TerminationError(e.to_string()))?;
+
+    select! {
+        _ = ctrl_c.recv() => {
+            debug!("Received Ctrl-C");
+        }
+        _ = ctrl_break.recv() => {
+            debug!("Received Ctrl-Break");
+        }
+        _ = ctrl_close.recv() => {
+            debug!("Received console close");
+        }
+        _ = ctrl_shutdown.recv() => {
+            debug!("Received system shutdown");
+        }
+    }
+
+    Ok(())
+}
+
+/// Wait for Ctrl-C, the only signal portable to the other platforms
+#[cfg(not(any(unix, windows)))]
+pub async fn wait_exit_signals() -> Result<(), TestrpcError> {
+    signal::ctrl_c()
+        .await
+        .map_err(|e| TestrpcError::TerminationError(e.to_string()))?;
+    debug!("Received Ctrl-C");
+    Ok(())
+}
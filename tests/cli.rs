@@ -0,0 +1,28 @@
+use std::process::Command;
+
+/// Run the binary with the given args, returning its exit code and stderr
+fn testrpc(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_testrpc"))
+        .args(args)
+        .env_remove("DRY_RUN")
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn test_bad_config() {
+    let path = std::env::temp_dir().join(format!("testrpc-bad-config-{}.yaml", std::process::id()));
+    std::fs::write(&path, "interval: [not a number\n").unwrap();
+    let (code, stderr) = testrpc(&["validate", "-f", &path.to_string_lossy()]);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("LoadConfigError"), "{stderr}");
+
+    let (code, stderr) = testrpc(&["--validate-only", "-f", "/nonexistent/testrpc.yaml"]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("LoadConfigError"), "{stderr}");
+}
@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use testrpc::adapters::grpc::pb::{
+    self,
+    health::{
+        health_check_response::ServingStatus,
+        health_server::{Health, HealthServer},
+        HealthCheckRequest, HealthCheckResponse,
+    },
+    tx_submission_server::{TxSubmission, TxSubmissionServer},
+};
+use testrpc::{config, runner};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status, Streaming};
+
+/// Tx-submission service accepting every tx it receives
+#[derive(Clone, Default)]
+struct MockService {
+    received: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl TxSubmission for MockService {
+    async fn submit_txs(
+        &self,
+        request: Request<Streaming<pb::Tx>>,
+    ) -> Result<Response<pb::SubmitSummary>, Status> {
+        let mut stream = request.into_inner();
+        let mut accepted = 0;
+        while stream.message().await?.is_some() {
+            accepted += 1;
+        }
+        self.received.fetch_add(accepted, Ordering::SeqCst);
+        Ok(Response::new(pb::SubmitSummary {
+            accepted: accepted as u64,
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl Health for MockService {
+    async fn check(
+        &self,
+        _request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        Ok(Response::new(HealthCheckResponse {
+            status: ServingStatus::Serving as i32,
+        }))
+    }
+}
+
+/// Spawns the mock service, returning its url
+async fn spawn_service(service: MockService) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(TxSubmissionServer::new(service.clone()))
+            .add_service(HealthServer::new(service))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_run_once_grpc() {
+    let service = MockService::default();
+    let url = spawn_service(service.clone()).await;
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 2
+adapter: grpc
+args:
+  endpoints:
+    - {url}
+round_templates: {{}}
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 5
+      tx_size: 10
+"#
+    ))
+    .unwrap();
+
+    let pings = runner::ping_endpoints(
+        cfg.adapter.clone(),
+        cfg.args.clone(),
+        vec![url.clone()],
+        None,
+        cfg.dry_run,
+    )
+    .await
+    .unwrap();
+    assert!(pings[0].1, "{pings:?}");
+
+    let results = runner::run_once(cfg).await.unwrap();
+    assert_eq!(results.total.sent, 10);
+    assert_eq!(results.total.bytes_sent, 100);
+    assert_eq!(results.total.failed, 0);
+    assert_eq!(service.received.load(Ordering::SeqCst), 10);
+}
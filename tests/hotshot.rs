@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde_yaml::Value;
+
+use testrpc::adapters::{hotshot::HotshotAdapter, Adapter};
+use testrpc::config::RoundTemplate;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+/// Spawns an HTTP server that answers every request with a JSON-RPC error
+async fn spawn_failing_endpoint() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 64 * 1024];
+                let _ = stream.read(&mut buf).await;
+                let body =
+                    r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"rejected"},"id":0}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_send_txs_failing_endpoint() {
+    let rpc_url = spawn_failing_endpoint().await;
+    let adapter = HotshotAdapter::try_from(HashMap::new()).unwrap();
+    let template = RoundTemplate {
+        txs: 3,
+        tx_size: 10,
+        latency: Some(std::time::Duration::from_millis(1)),
+        ..Default::default()
+    };
+    let results = adapter
+        .send_txs(&rpc_url, 0, 0, &template, None, &CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(results.sent, 0);
+    assert_eq!(results.failed, 3);
+    assert_eq!(results.bytes_failed, 30);
+}
+
+/// Spawns an HTTP server that drops the first `drops` connections without answering,
+/// then answers every request with a JSON-RPC success. Returns the url and the number
+/// of accepted connections.
+async fn spawn_flaky_endpoint(drops: usize) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let accepted_cloned = accepted.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            if accepted_cloned.fetch_add(1, Ordering::SeqCst) < drops {
+                drop(stream);
+                continue;
+            }
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 64 * 1024];
+                let _ = stream.read(&mut buf).await;
+                let body = r#"{"jsonrpc":"2.0","result":null,"id":0}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    (format!("http://{addr}"), accepted)
+}
+
+#[tokio::test]
+async fn test_send_txs_retries_network_errors() {
+    let (rpc_url, accepted) = spawn_flaky_endpoint(2).await;
+    let args = HashMap::from([("send_retries".to_string(), Value::from(2))]);
+    let adapter = HotshotAdapter::try_from(args).unwrap();
+    let template = RoundTemplate {
+        txs: 3,
+        tx_size: 10,
+        ..Default::default()
+    };
+    let results = adapter
+        .send_txs(&rpc_url, 0, 0, &template, None, &CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(results.sent, 3);
+    assert_eq!(results.failed, 0);
+    assert_eq!(accepted.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_send_txs_does_not_retry_rpc_errors() {
+    let rpc_url = spawn_failing_endpoint().await;
+    let args = HashMap::from([("send_retries".to_string(), Value::from(2))]);
+    let adapter = HotshotAdapter::try_from(args).unwrap();
+    let template = RoundTemplate {
+        txs: 3,
+        tx_size: 10,
+        ..Default::default()
+    };
+    let start = std::time::Instant::now();
+    let results = adapter
+        .send_txs(&rpc_url, 0, 0, &template, None, &CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(results.failed, 3);
+    // no backoff delay was waited
+    assert!(start.elapsed() < std::time::Duration::from_millis(100));
+}
+
+/// Spawns an HTTP server answering every request with the given body, returning its url
+/// and the requests it got
+async fn spawn_coordinator(body: &'static str) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let requests_cloned = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let requests = requests_cloned.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 64 * 1024];
+                let n = stream.read(&mut buf).await.unwrap_or_default();
+                requests
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf[..n]).to_string());
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    (format!("http://{addr}"), requests)
+}
+
+#[tokio::test]
+async fn test_load_endpoints_coordinator_failover() {
+    // nothing listens on the first coordinator
+    let down = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let down_url = format!("http://{}", down.local_addr().unwrap());
+    drop(down);
+    let (up_url, _) = spawn_coordinator(
+        "/ip4/10.0.0.1/udp/3000/quic-v1\n/ip4/10.0.0.2/udp/3000/quic-v1\n/ip4/10.0.0.1/udp/3000/quic-v1",
+    )
+    .await;
+    let args = HashMap::from([
+        ("coordinator_url".to_string(), Value::String(down_url)),
+        (
+            "coordinator_urls".to_string(),
+            Value::Sequence(vec![Value::String(up_url)]),
+        ),
+    ]);
+    let adapter = HotshotAdapter::try_from(args.clone()).unwrap();
+    let rpc_urls = adapter.load_endpoints(args).await.unwrap();
+    assert_eq!(
+        rpc_urls,
+        vec!["http://10.0.0.1:5000", "http://10.0.0.2:5000"]
+    );
+}
+
+#[tokio::test]
+async fn test_load_endpoints_coordinator_headers() {
+    // the coordinator is queried with the client of the adapter
+    let (url, requests) = spawn_coordinator("/ip4/10.0.0.1/udp/3000/quic-v1").await;
+    let headers = serde_yaml::from_str("authorization: Bearer token").unwrap();
+    let args = HashMap::from([
+        ("coordinator_url".to_string(), Value::String(url)),
+        ("headers".to_string(), headers),
+    ]);
+    let adapter = HotshotAdapter::try_from(args.clone()).unwrap();
+    adapter.load_endpoints(args).await.unwrap();
+    let request = requests.lock().unwrap()[0].to_lowercase();
+    assert!(request.contains("authorization: bearer token"), "{request}");
+}
@@ -0,0 +1,163 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{SinkExt as _, StreamExt as _};
+use tokio_tungstenite::tungstenite::Message;
+
+use testrpc::{common::TestrpcError, jrpc};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn test_send_timeout() {
+    // accept connections but never answer
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut streams = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            streams.push(stream);
+        }
+    });
+    let res = jrpc::send(
+        &format!("http://{addr}"),
+        0,
+        "send_txs",
+        serde_json::json!({}),
+        Some(Duration::from_millis(100)),
+    )
+    .await;
+    match res {
+        Err(TestrpcError::RequestFailed {
+            endpoint, cause, ..
+        }) => {
+            assert_eq!(endpoint, format!("http://{addr}"));
+            assert!(cause.to_string().contains("timed out"), "{cause}");
+        }
+        res => panic!("Expected a timeout error, got {res:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_send_headers() {
+    // answer one request, forwarding the raw request
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 64 * 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let body = r#"{"jsonrpc":"2.0","result":{},"id":1}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_string());
+    });
+    let client = jrpc::Client::new(&jrpc::ClientOptions {
+        headers: [("Authorization".to_string(), "Bearer token".to_string())].into(),
+        ..Default::default()
+    })
+    .unwrap();
+    client
+        .send(
+            &format!("http://{addr}"),
+            1,
+            "send_txs",
+            serde_json::json!({}),
+            None,
+        )
+        .await
+        .unwrap();
+    let request = rx.await.unwrap().to_lowercase();
+    assert!(request.contains("authorization: bearer token"), "{request}");
+}
+
+#[tokio::test]
+async fn test_send_ws_shared_connection() {
+    // answer each pair of requests in reverse order, `result` being the request id
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let connections_cloned = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            connections_cloned.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let mut ids = Vec::new();
+                while let Some(Ok(Message::Text(text))) = ws.next().await {
+                    let request: serde_json::Value = serde_json::from_str(text.as_str()).unwrap();
+                    ids.push(request["id"].as_u64().unwrap());
+                    if ids.len() == 2 {
+                        for id in ids.drain(..).rev() {
+                            let response =
+                                format!(r#"{{"jsonrpc":"2.0","result":{id},"id":{id}}}"#);
+                            ws.send(Message::text(response)).await.unwrap();
+                        }
+                    }
+                }
+            });
+        }
+    });
+    let client = jrpc::Client::new(&jrpc::ClientOptions::default()).unwrap();
+    let url = format!("ws://{addr}");
+    let send = |id| client.send(&url, id, "send_txs", serde_json::json!({}), None);
+    let (first, second) = tokio::join!(send(1), send(2));
+    assert_eq!(first.unwrap().id(), 1);
+    assert_eq!(second.unwrap().id(), 2);
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_send_ws_duplicate_ids() {
+    // answer each pair of requests in reverse order, `result` being the request params
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let wire_ids = Arc::new(Mutex::new(Vec::new()));
+    let server_ids = wire_ids.clone();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let mut requests = Vec::new();
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            let request = serde_json::from_str::<serde_json::Value>(text.as_str()).unwrap();
+            server_ids
+                .lock()
+                .unwrap()
+                .push(request["id"].as_u64().unwrap());
+            requests.push(request);
+            if requests.len() == 2 {
+                for request in requests.drain(..).rev() {
+                    let response = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "result": request["params"],
+                        "id": request["id"],
+                    });
+                    ws.send(Message::text(response.to_string())).await.unwrap();
+                }
+            }
+        }
+    });
+    let client = jrpc::Client::new(&jrpc::ClientOptions::default()).unwrap();
+    let url = format!("ws://{addr}");
+    let send = |params| client.send(&url, 7, "send_txs", params, None);
+    let (first, second) = tokio::join!(send(serde_json::json!([1])), send(serde_json::json!([2])));
+    let (first, second) = (first.unwrap(), second.unwrap());
+    // each request gets its own response, with the id it was sent with
+    assert_eq!(first.result(), &serde_json::json!([1]));
+    assert_eq!(second.result(), &serde_json::json!([2]));
+    assert_eq!((first.id(), second.id()), (7, 7));
+    // the first one is sent with its own id, only the one in flight with the same id is renamed
+    let wire_ids = wire_ids.lock().unwrap().clone();
+    assert_eq!(
+        wire_ids.iter().filter(|id| **id == 7).count(),
+        1,
+        "{wire_ids:?}"
+    );
+    assert_eq!(wire_ids.len(), 2);
+}
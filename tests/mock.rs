@@ -0,0 +1,149 @@
+use testrpc::{config, mock::MockRpcServer, runner};
+
+#[tokio::test]
+async fn test_run_once_against_mock_servers() {
+    let servers = [
+        MockRpcServer::start().await.unwrap(),
+        MockRpcServer::start().await.unwrap(),
+        MockRpcServer::start().await.unwrap(),
+    ];
+    let rpcs = servers
+        .iter()
+        .map(|server| format!("  - {}", server.url()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 4
+adapter: hotshot
+args: {{}}
+round_templates: {{}}
+rpcs:
+{rpcs}
+rounds:
+  - rpcs: [0, 1]
+    template:
+      txs: 5
+      tx_size: 10
+  - rpcs: [1, 2]
+    template:
+      txs: 3
+      tx_size: 20
+"#
+    ))
+    .unwrap();
+    let results = runner::run_once(cfg).await.unwrap();
+    assert_eq!(results.total.sent, 2 * (2 * 5 + 2 * 3));
+    assert_eq!(results.total.failed, 0);
+    // each iteration sends one request per rpc
+    assert_eq!(servers[0].received_requests(), 2);
+    assert_eq!(servers[0].received_txs(), 10);
+    assert_eq!(servers[0].received_bytes(), 100);
+    assert_eq!(servers[1].received_txs(), 16);
+    assert_eq!(servers[1].received_bytes(), 220);
+    assert_eq!(servers[2].received_txs(), 6);
+    assert_eq!(servers[2].received_bytes(), 120);
+}
+
+#[tokio::test]
+async fn test_batch_against_mock_server() {
+    let server = MockRpcServer::start().await.unwrap();
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 1
+adapter: hotshot
+args:
+  batch: true
+round_templates: {{}}
+rpcs:
+  - {}
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 4
+      tx_size: 10
+"#,
+        server.url()
+    ))
+    .unwrap();
+    let results = runner::run_once(cfg).await.unwrap();
+    assert_eq!(results.total.sent, 4);
+    // one request per tx, in a single batch
+    assert_eq!(server.received_requests(), 4);
+    assert_eq!(server.received_txs(), 4);
+}
+
+#[tokio::test]
+async fn test_collect_status_against_mock_server() {
+    let server = MockRpcServer::start().await.unwrap();
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 2
+adapter: hotshot
+args: {{}}
+collect_status: true
+round_templates: {{}}
+rpcs:
+  - {}
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 3
+      tx_size: 10
+"#,
+        server.url()
+    ))
+    .unwrap();
+    let results = runner::run_once(cfg).await.unwrap();
+    // the status is fetched once the txs of the round were received
+    let heights = results
+        .rounds
+        .iter()
+        .map(|round| round.node_status[&server.url()].height)
+        .collect::<Vec<_>>();
+    assert_eq!(heights, vec![Some(3), Some(6)]);
+}
+
+#[tokio::test]
+async fn test_replay_tx_file_against_mock_server() {
+    let server = MockRpcServer::start().await.unwrap();
+    let path = std::env::temp_dir().join(format!("testrpc-mock-txs-{}", std::process::id()));
+    // a 2 bytes tx in hex and a 3 bytes tx in base64
+    std::fs::write(&path, "0102\nAQID\n").unwrap();
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 1
+adapter: hotshot
+args: {{}}
+round_templates: {{}}
+rpcs:
+  - {}
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 5
+      tx_size: 100
+      tx_source:
+        type: file
+        path: {}
+"#,
+        server.url(),
+        path.display()
+    ))
+    .unwrap();
+    let results = runner::run_once(cfg).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // cycles through the file, tx_size is ignored
+    assert_eq!(results.total.sent, 5);
+    assert_eq!(results.total.bytes_sent, 2 + 3 + 2 + 3 + 2);
+    assert_eq!(server.received_txs(), 5);
+    assert_eq!(server.received_bytes(), 12);
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use testrpc::{config, ctx, mock::MockRpcServer, runner};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Spawns a server accepting connections but never answering
+async fn spawn_hung_endpoint() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut streams = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            streams.push(stream);
+        }
+    });
+    format!("http://{addr}")
+}
+
+/// Spawns a server answering every request with a JSON-RPC error
+async fn spawn_failing_endpoint() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 64 * 1024];
+                let _ = stream.read(&mut buf).await;
+                let body =
+                    r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"rejected"},"id":0}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_adaptive_load_backs_off() {
+    let failing = spawn_failing_endpoint().await;
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 3
+adapter: hotshot
+args: {{}}
+adaptive:
+  failure_threshold: 0.5
+  backoff_factor: 0.5
+round_templates: {{}}
+rpcs:
+  - {failing}
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 8
+      tx_size: 10
+"#
+    ))
+    .unwrap();
+    let results = runner::run_once(cfg).await.unwrap();
+    assert_eq!(results.load_trajectory, vec![1.0, 0.5, 0.25]);
+    let failed = results
+        .rounds
+        .iter()
+        .map(|round| round.failed)
+        .collect::<Vec<_>>();
+    assert_eq!(failed, vec![8, 4, 2]);
+    assert_eq!(results.first_failure_iteration, Some(1));
+    assert!(results.first_failure_elapsed.is_some());
+}
+
+#[tokio::test]
+async fn test_adaptive_load_per_pass() {
+    // half of the txs of each pass fail, the load only changes once the pass completed
+    let server = MockRpcServer::start().await.unwrap();
+    let failing = spawn_failing_endpoint().await;
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 4
+adapter: hotshot
+args: {{}}
+adaptive:
+  failure_threshold: 0.4
+  backoff_factor: 0.5
+round_templates: {{}}
+rpcs:
+  - {failing}
+  - {}
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 8
+      tx_size: 10
+  - rpcs: [1]
+    template:
+      txs: 8
+      tx_size: 10
+"#,
+        server.url()
+    ))
+    .unwrap();
+    let results = runner::run_once(cfg).await.unwrap();
+    assert_eq!(results.load_trajectory, vec![1.0, 1.0, 0.5, 0.5]);
+}
+
+#[tokio::test]
+async fn test_shutdown_grace_aborts_stuck_round() {
+    let server = MockRpcServer::start().await.unwrap();
+    let hung = spawn_hung_endpoint().await;
+    let cfg = config::parse_config_yaml(&format!(
+        r#"
+interval: 0
+back_to_back: true
+iterations: 10
+adapter: hotshot
+args: {{}}
+shutdown_grace: 200ms
+round_templates: {{}}
+rpcs:
+  - {}
+  - {hung}
+rounds:
+  - rpcs: [0]
+    template:
+      txs: 2
+      tx_size: 10
+  - rpcs: [1]
+    template:
+      txs: 3
+      tx_size: 10
+"#,
+        server.url()
+    ))
+    .unwrap();
+    let rpc_urls = cfg.rpcs.clone().unwrap();
+    let ctx = Arc::new(ctx::Context::new());
+    let ctx_cloned = ctx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        ctx_cloned.stop(ctx::StopReason::Signal);
+    });
+    let start = Instant::now();
+    let results = runner::run(ctx, cfg, rpc_urls).await.unwrap();
+    // way before the request timeout
+    assert!(start.elapsed() < Duration::from_secs(5));
+    // the completed round is kept, the txs of the aborted one are failed
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].sent, 2);
+    assert_eq!(results[1].sent, 0);
+    assert_eq!(results[1].failed, 3);
+    assert_eq!(results[1].bytes_failed, 30);
+}